@@ -35,6 +35,7 @@ fn make_candidates(n: usize) -> Vec<Candidate> {
                 weight: TXIN_BASE_WEIGHT + P2WPKH_SAT_W,
                 input_count: 1,
                 is_segwit: true,
+                ancestors: None,
             }
         })
         .collect()
@@ -48,6 +49,9 @@ fn make_bnb_inputs(candidates: &[Candidate]) -> (Target, FeeRate) {
         fee: TargetFee::from_feerate(target_fr),
         outputs: TargetOutputs::fund_outputs([(TXOUT_BASE_WEIGHT + TR_SPK_WEIGHT, total / 2)]),
         max_weight: None,
+        min_input_count: None,
+        max_input_count: None,
+        truc: false,
     };
     (target, long_term_fr)
 }
@@ -83,7 +87,7 @@ fn bench_run_bnb_lowest_fee(c: &mut Criterion) {
                     let metric = LowestFee {
                         long_term_feerate,
                         dust_relay_feerate: FeeRate::from_sat_per_vb(1.0),
-                        drain_weights: DrainWeights::TR_KEYSPEND,
+                        drain_options: vec![DrainWeights::TR_KEYSPEND],
                     };
                     let _ = sel.run_bnb(target, metric, black_box(100_000));
                     sel
@@ -1,67 +1,159 @@
 use core::cmp::Reverse;
 
-use crate::{float::Ordf32, Drain, Target};
+use crate::{
+    float::Ordf32, BnbBudget, BnbObserver, BnbProgress, BnbSolution, BudgetLimit, Drain, Target,
+};
 
 use super::CoinSelector;
-use alloc::collections::BinaryHeap;
+use alloc::{
+    collections::{BTreeMap, BTreeSet, BinaryHeap},
+    vec::Vec,
+};
+
+/// Options for [`CoinSelector::run_bnb_with`] and [`CoinSelector::bnb_solutions_with`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BnbOptions<B, O, S = Ordf32> {
+    /// When to stop searching. See [`BnbBudget`].
+    pub budget: B,
+    /// Gets told about every step of the search. See [`BnbObserver`].
+    pub observer: O,
+    /// The order branches are explored in.
+    pub exploration: Exploration,
+    /// The most branches that can be queued, if any.
+    ///
+    /// When the queue is full, the branch with the worst lower bound is dropped. The search then
+    /// uses bounded memory but it may miss the best solution.
+    pub max_queue_len: Option<usize>,
+    /// Only look for solutions that score lower than this, e.g. the score of a selection you
+    /// already have. Branches that can't beat it are pruned from the start.
+    ///
+    /// See also [`CoinSelector::run_bnb_with_incumbent`].
+    pub upper_bound: Option<S>,
+}
+
+impl<B: BnbBudget, O, S> BnbOptions<B, O, S> {
+    /// Options with a `budget` and an `observer` that explore [`Exploration::BestFirst`] with an
+    /// unbounded queue and no upper bound.
+    pub fn new(budget: B, observer: O) -> Self {
+        BnbOptions {
+            budget,
+            observer,
+            exploration: Exploration::BestFirst,
+            max_queue_len: None,
+            upper_bound: None,
+        }
+    }
+}
+
+/// The order branch and bound explores branches in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Exploration {
+    /// Always explore the queued branch with the lowest lower bound next.
+    ///
+    /// This does the fewest rounds, but the queue can grow very large when there are many
+    /// candidates.
+    BestFirst,
+    /// Explore the most recently queued branch next, trying the branch with the lower bound first.
+    ///
+    /// Only O(number of candidates) branches are ever queued, but it can take more rounds to find
+    /// the best solution.
+    DepthFirst,
+}
 
 /// An [`Iterator`] that iterates over rounds of branch and bound to minimize the score of the
 /// provided [`BnbMetric`].
+///
+/// The iterator also ends if the `budget` runs out, in which case `exhausted` says which limit was
+/// reached.
+///
+/// It yields every selection that scores among the `k` best so far (just one by default) and
+/// prunes the branches that can't beat the `k`th best.
 #[derive(Debug)]
-pub(crate) struct BnbIter<'a, M: BnbMetric> {
-    queue: BinaryHeap<Branch<'a>>,
-    best: Option<Ordf32>,
+pub(crate) struct BnbIter<'a, M: BnbMetric, B: BnbBudget = (), O: BnbObserver<M::Score> = ()> {
+    queue: Queue<'a, M::Score>,
+    pub(crate) max_queue_len: Option<usize>,
+    /// Only solutions that score lower than this are yielded.
+    best: Option<M::Score>,
+    k: usize,
+    /// The scores of the (up to) `k` best solutions, lowest first.
+    top_scores: Vec<M::Score>,
     /// The target the metric scores selections against.
     pub(crate) target: Target,
     /// The `BnBMetric` that will score each selection
     pub(crate) metric: M,
+    /// Only `None` while a round is being done with it.
+    budget: Option<B>,
+    observer: O,
+    /// How far the search has got.
+    pub(crate) progress: BnbProgress,
+    /// The limit of the budget that stopped the search, if it was stopped.
+    pub(crate) exhausted: Option<BudgetLimit>,
+    /// The lowest lower bound of the branches dropped because the queue was full, if any were.
+    pub(crate) dropped_lower_bound: Option<M::Score>,
 }
 
-impl<'a, M: BnbMetric> Iterator for BnbIter<'a, M> {
-    type Item = Option<(CoinSelector<'a>, Ordf32)>;
+impl<'a, M: BnbMetric, B: BnbBudget, O: BnbObserver<M::Score>> Iterator for BnbIter<'a, M, B, O> {
+    type Item = Option<(CoinSelector<'a>, M::Score)>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        // {
-        //     println!("=========================== {:?}", self.best);
-        //     for thing in self.queue.iter() {
-        //         println!("{} {:?}", &thing.selector, thing.lower_bound);
-        //     }
-        //     let _ = std::io::stdin().read_line(&mut alloc::string::String::new());
-        // }
-
-        let branch = self.queue.pop()?;
-        if let Some(best) = &self.best {
-            // If the next thing in queue is not better than our best we're done.
-            if *best < branch.lower_bound {
-                // println!(
-                //     "\t\t(SKIP) branch={} inclusion={} lb={:?}, score={:?}",
-                //     branch.selector,
-                //     !branch.is_exclusion,
-                //     branch.lower_bound,
-                //     self.metric.score(&branch.selector),
-                // );
-                return None;
+        let mut budget = self.budget.take().expect("only taken during a round");
+        let item = self.next_with(&mut budget);
+        self.budget = Some(budget);
+        item
+    }
+}
+
+impl<'a, M: BnbMetric, B: BnbBudget, O: BnbObserver<M::Score>> BnbIter<'a, M, B, O> {
+    /// Does a round like [`Iterator::next`] but checks `budget` instead of the iterator's own.
+    pub(crate) fn next_with<BB: BnbBudget + ?Sized>(
+        &mut self,
+        budget: &mut BB,
+    ) -> Option<Option<(CoinSelector<'a>, M::Score)>> {
+        let branch = loop {
+            let branch = self.queue.pop()?;
+            match self.best {
+                Some(best) if best < branch.lower_bound => match self.queue.exploration() {
+                    // If the next thing in queue is not better than our best we're done, and so
+                    // is everything after it.
+                    Exploration::BestFirst => {
+                        self.observer.dropped(&branch.selector, branch.lower_bound);
+                        while let Some(branch) = self.queue.pop() {
+                            self.observer.dropped(&branch.selector, branch.lower_bound);
+                        }
+                        return None;
+                    }
+                    // Otherwise our best got better since this was queued.
+                    Exploration::DepthFirst => {
+                        self.observer.dropped(&branch.selector, branch.lower_bound);
+                    }
+                },
+                _ => break branch,
             }
+        };
+
+        // Only check the budget when there is something left to do, so that a search that
+        // finished is never reported as stopped.
+        if let Some(limit) = budget.check(self.progress) {
+            self.exhausted = Some(limit);
+            self.queue.push(branch);
+            return None;
         }
-        // println!(
-        //     "\t\t( POP) branch={} inclusion={} lb={:?}, score={:?}",
-        //     branch.selector,
-        //     !branch.is_exclusion,
-        //     branch.lower_bound,
-        //     self.metric.score(&branch.selector),
-        // );
+        self.exhausted = None;
+        self.progress.rounds += 1;
+        self.observer.popped(&branch.selector, branch.lower_bound);
 
         let selector = branch.selector;
 
         let mut return_val = None;
-        if !branch.is_exclusion {
+        // an exclusion branch has the same selection as its parent, which has been scored already
+        if !branch.is_exclusion && selector.is_conflicting() {
             if let Some(score) = self.metric.score(&selector, self.target) {
                 let better = match self.best {
                     Some(best_score) => score < best_score,
                     None => true,
                 };
                 if better {
-                    self.best = Some(score);
+                    self.record(&selector, score);
                     return_val = Some(score);
                 }
             };
@@ -70,142 +162,388 @@ impl<'a, M: BnbMetric> Iterator for BnbIter<'a, M> {
         self.insert_new_branches(&selector);
         Some(return_val.map(|score| (selector, score)))
     }
-}
 
-impl<'a, M: BnbMetric> BnbIter<'a, M> {
-    pub(crate) fn new(mut selector: CoinSelector<'a>, target: Target, metric: M) -> Self {
+    pub(crate) fn new(
+        mut selector: CoinSelector<'a>,
+        target: Target,
+        metric: M,
+        options: BnbOptions<B, O, M::Score>,
+    ) -> Self {
         let mut iter = BnbIter {
-            queue: BinaryHeap::default(),
-            best: None,
+            queue: Queue::new(options.exploration, options.max_queue_len),
+            max_queue_len: options.max_queue_len,
+            best: options.upper_bound,
+            k: 1,
+            top_scores: Vec::new(),
             target,
             metric,
+            budget: Some(options.budget),
+            observer: options.observer,
+            progress: BnbProgress::default(),
+            exhausted: None,
+            dropped_lower_bound: None,
         };
 
         if iter.metric.requires_ordering_by_descending_value_pwu() {
             selector.sort_candidates_by_descending_value_pwu();
         }
 
-        iter.consider_adding_to_queue(&selector, false);
+        if let Some(branch) = iter.bound_branch(&selector, false) {
+            iter.push(branch);
+        }
 
         iter
     }
 
-    fn consider_adding_to_queue(&mut self, cs: &CoinSelector<'a>, is_exclusion: bool) {
-        let bound = self.metric.bound(cs, self.target);
-        if let Some(bound) = bound {
-            let is_good_enough = match self.best {
-                Some(best) => best > bound,
-                None => true,
-            };
-            if is_good_enough {
-                let branch = Branch {
-                    lower_bound: bound,
-                    selector: cs.clone(),
-                    is_exclusion,
-                };
-                /*println!(
-                    "\t\t(PUSH) branch={} inclusion={} lb={:?} score={:?}",
-                    branch.selector,
-                    !branch.is_exclusion,
-                    branch.lower_bound,
-                    self.metric.score(&branch.selector),
-                );*/
-                self.queue.push(branch);
-            } /* else {
-                  println!(
-                      "\t\t( REJ) branch={} inclusion={} lb={:?} score={:?}",
-                      cs,
-                      !is_exclusion,
-                      bound,
-                      self.metric.score(cs),
-                  );
-              }*/
-        } /*else {
-              println!(
-                  "\t\t(NO B) branch={} inclusion={} score={:?}",
-                  cs,
-                  !is_exclusion,
-                  self.metric.score(cs),
-              );
-          }*/
+    /// Keep the `k` best solutions instead of just the best. Must be set before the search starts.
+    pub(crate) fn with_top_k(mut self, k: usize) -> Self {
+        debug_assert!(k > 0 && self.progress.rounds == 0);
+        self.k = k;
+        self
+    }
+
+    fn record(&mut self, cs: &CoinSelector<'a>, score: M::Score) {
+        let position = self.top_scores.partition_point(|&top| top <= score);
+        if position == 0 {
+            self.observer.improved(cs, score);
+        }
+        self.top_scores.insert(position, score);
+        self.top_scores.truncate(self.k);
+        if self.top_scores.len() == self.k {
+            self.best = self.top_scores.last().copied();
+        }
+    }
+
+    /// The branch for `cs` if it may have a better solution than the best so far.
+    fn bound_branch(
+        &mut self,
+        cs: &CoinSelector<'a>,
+        is_exclusion: bool,
+    ) -> Option<Branch<'a, M::Score>> {
+        self.progress.nodes += 1;
+        // A branch that can't conflict has no solutions whatever the metric says.
+        let bound = if cs.can_conflict() {
+            self.metric.bound(cs, self.target)
+        } else {
+            None
+        };
+        let bound = match bound {
+            Some(bound) => bound,
+            None => {
+                self.observer.no_bound(cs);
+                return None;
+            }
+        };
+        let is_good_enough = match self.best {
+            Some(best) => best > bound,
+            None => true,
+        };
+        if !is_good_enough {
+            self.observer.pruned(cs, bound);
+            return None;
+        }
+        Some(Branch {
+            lower_bound: bound,
+            selector: cs.clone(),
+            is_exclusion,
+        })
+    }
+
+    fn push(&mut self, branch: Branch<'a, M::Score>) {
+        if self
+            .max_queue_len
+            .map_or(false, |max| self.queue.len() >= max)
+        {
+            // The queue is full so we drop whichever is worse: `branch` or the worst queued one.
+            if self.queue.worst().map_or(true, |worst| branch <= *worst) {
+                self.observer.pruned(&branch.selector, branch.lower_bound);
+                self.lower_dropped_bound(branch.lower_bound);
+                return;
+            }
+            let worst = self.queue.remove_worst();
+            self.observer.dropped(&worst.selector, worst.lower_bound);
+            self.lower_dropped_bound(worst.lower_bound);
+        }
+        self.observer
+            .pushed(&branch.selector, branch.lower_bound, self.queue.len() + 1);
+        self.queue.push(branch);
+    }
+
+    /// Remembers that a branch with `lower_bound` was left out of a full queue.
+    fn lower_dropped_bound(&mut self, lower_bound: M::Score) {
+        self.dropped_lower_bound = Some(match self.dropped_lower_bound {
+            Some(dropped) => dropped.min(lower_bound),
+            None => lower_bound,
+        });
+    }
+
+    /// The lowest lower bound of the branches that are left to search or were dropped from a full
+    /// queue, i.e. no selection the search hasn't found yet scores lower than this. `None` if there
+    /// are no such branches.
+    pub(crate) fn unsearched_lower_bound(&self) -> Option<M::Score> {
+        let queued = self.queue.best().map(|branch| branch.lower_bound);
+        match (queued, self.dropped_lower_bound) {
+            (Some(queued), Some(dropped)) => Some(queued.min(dropped)),
+            (queued, dropped) => queued.or(dropped),
+        }
+    }
+
+    /// The [`BnbSolution`] for a `selector` with `score` that the search found, with what the
+    /// search has proven about how far it could be from the best solution.
+    pub(crate) fn solution(
+        &mut self,
+        selector: CoinSelector<'a>,
+        score: M::Score,
+    ) -> BnbSolution<'a, M::Score> {
+        let unsearched = self.unsearched_lower_bound();
+        BnbSolution {
+            drain: self.metric.drain(&selector, self.target),
+            selector,
+            score,
+            lower_bound: unsearched.map_or(score, |bound| bound.min(score)),
+            proven_optimal: unsearched.map_or(true, |bound| bound >= score),
+        }
     }
 
     fn insert_new_branches(&mut self, cs: &CoinSelector<'a>) {
-        let (next_index, next) = match cs.unselected().next() {
-            Some(c) => c,
+        let (inclusion_cs, exclusion_cs) = match split(cs) {
+            Some(split) => split,
             None => return, // exhausted
         };
+        let inclusion = self.bound_branch(&inclusion_cs, false);
+        let exclusion = self.bound_branch(&exclusion_cs, true);
 
-        let mut inclusion_cs = cs.clone();
-        inclusion_cs.select(next_index);
-        self.consider_adding_to_queue(&inclusion_cs, false);
-
-        // for the exclusion branch, we keep banning if candidates have the same weight and value
-        let mut is_first_ban = true;
-        let mut exclusion_cs = cs.clone();
-        let to_ban = (next.value, next.weight);
-        for (next_index, next) in cs.unselected() {
-            if (next.value, next.weight) != to_ban {
-                break;
+        let (first, second) = match (self.queue.exploration(), inclusion, exclusion) {
+            // Push the better branch last so depth first explores it first.
+            (Exploration::DepthFirst, Some(inclusion), Some(exclusion))
+                if inclusion > exclusion =>
+            {
+                (Some(exclusion), Some(inclusion))
             }
-            let (_index, _candidate) = exclusion_cs
-                .candidates()
-                .find(|(i, _)| *i == next_index)
-                .expect("must have index since we are planning to ban it");
-            if is_first_ban {
-                is_first_ban = false;
-            } /*else {
-                  println!("banning: [{}] {:?}", _index, _candidate);
-              }*/
-            exclusion_cs.ban(next_index);
+            (_, inclusion, exclusion) => (inclusion, exclusion),
+        };
+        for branch in first.into_iter().chain(second) {
+            self.push(branch);
+        }
+    }
+}
+
+/// Splits `cs` into the selection that includes its next unselected candidate and the one that
+/// excludes it, or returns `None` if every candidate has been decided on.
+pub(crate) fn split<'a>(cs: &CoinSelector<'a>) -> Option<(CoinSelector<'a>, CoinSelector<'a>)> {
+    let (next_index, next) = cs.unselected().next()?;
+
+    let mut inclusion_cs = cs.clone();
+    inclusion_cs.select(next_index);
+
+    // for the exclusion branch, we keep banning the candidates that are the same as the excluded one
+    // since selecting one of them instead would lead to the same selections. A candidate that is
+    // one of the conflicts isn't the same as one that isn't.
+    let mut exclusion_cs = cs.clone();
+    let to_ban = (next, cs.conflicts().contains(next_index));
+    for (next_index, next) in cs.unselected() {
+        if (next, cs.conflicts().contains(next_index)) != to_ban {
+            break;
         }
-        self.consider_adding_to_queue(&exclusion_cs, true);
+        exclusion_cs.ban(next_index);
     }
+
+    Some((inclusion_cs, exclusion_cs))
 }
 
 #[derive(Debug, Clone)]
-struct Branch<'a> {
-    lower_bound: Ordf32,
+struct Branch<'a, S> {
+    lower_bound: S,
     selector: CoinSelector<'a>,
     is_exclusion: bool,
 }
 
-impl Ord for Branch<'_> {
+impl<S: Copy> Branch<'_, S> {
+    /// Better branches have greater priorities.
+    ///
+    /// NOTE: Reverse comparision `lower_bound` because we want a min-heap (by default BinaryHeap
+    /// is a max-heap).
+    /// NOTE: We tiebreak equal scores based on whether it's exlusion or not (preferring
+    /// inclusion). We do this because we want to try and get to evaluating complete selection
+    /// returning actual scores as soon as possible.
+    fn priority(&self) -> (Reverse<S>, bool) {
+        (Reverse(self.lower_bound), !self.is_exclusion)
+    }
+}
+
+impl<S: Ord + Copy> Ord for Branch<'_, S> {
     fn cmp(&self, other: &Self) -> core::cmp::Ordering {
-        // NOTE: Reverse comparision `lower_bound` because we want a min-heap (by default BinaryHeap
-        // is a max-heap).
-        // NOTE: We tiebreak equal scores based on whether it's exlusion or not (preferring
-        // inclusion). We do this because we want to try and get to evaluating complete selection
-        // returning actual scores as soon as possible.
-        core::cmp::Ord::cmp(
-            &(Reverse(&self.lower_bound), !self.is_exclusion),
-            &(Reverse(&other.lower_bound), !other.is_exclusion),
-        )
+        self.priority().cmp(&other.priority())
     }
 }
 
-impl PartialOrd for Branch<'_> {
+impl<S: Ord + Copy> PartialOrd for Branch<'_, S> {
     fn partial_cmp(&self, other: &Self) -> Option<core::cmp::Ordering> {
         Some(self.cmp(other))
     }
 }
 
-impl PartialEq for Branch<'_> {
+impl<S: Ord + Copy> PartialEq for Branch<'_, S> {
     fn eq(&self, other: &Self) -> bool {
-        self.lower_bound == other.lower_bound
+        self.priority() == other.priority()
     }
 }
 
-impl Eq for Branch<'_> {}
+impl<S: Ord + Copy> Eq for Branch<'_, S> {}
+
+/// The queued branches, in the order of the [`Exploration`].
+#[derive(Debug)]
+enum Queue<'a, S> {
+    BestFirst(BinaryHeap<Branch<'a, S>>),
+    DepthFirst(Vec<Branch<'a, S>>),
+    /// A queue with a length cap, which has to find and remove its worst branch whenever it's full.
+    Capped(CappedQueue<'a, S>),
+}
+
+impl<'a, S: Ord + Copy> Queue<'a, S> {
+    fn new(exploration: Exploration, max_queue_len: Option<usize>) -> Self {
+        match (exploration, max_queue_len) {
+            (_, Some(_)) => Queue::Capped(CappedQueue::new(exploration)),
+            (Exploration::BestFirst, None) => Queue::BestFirst(BinaryHeap::default()),
+            (Exploration::DepthFirst, None) => Queue::DepthFirst(Vec::new()),
+        }
+    }
+
+    fn exploration(&self) -> Exploration {
+        match self {
+            Queue::BestFirst(_) => Exploration::BestFirst,
+            Queue::DepthFirst(_) => Exploration::DepthFirst,
+            Queue::Capped(queue) => queue.exploration,
+        }
+    }
+
+    fn push(&mut self, branch: Branch<'a, S>) {
+        match self {
+            Queue::BestFirst(heap) => heap.push(branch),
+            Queue::DepthFirst(stack) => stack.push(branch),
+            Queue::Capped(queue) => queue.push(branch),
+        }
+    }
+
+    fn pop(&mut self) -> Option<Branch<'a, S>> {
+        match self {
+            Queue::BestFirst(heap) => heap.pop(),
+            Queue::DepthFirst(stack) => stack.pop(),
+            Queue::Capped(queue) => queue.pop(),
+        }
+    }
+
+    fn len(&self) -> usize {
+        match self {
+            Queue::BestFirst(heap) => heap.len(),
+            Queue::DepthFirst(stack) => stack.len(),
+            Queue::Capped(queue) => queue.branches.len(),
+        }
+    }
 
-/// A branch and bound metric where we minimize the [`Ordf32`] score.
+    /// The branch with the lowest lower bound.
+    fn best(&self) -> Option<&Branch<'a, S>> {
+        match self {
+            Queue::BestFirst(heap) => heap.peek(),
+            Queue::DepthFirst(stack) => stack.iter().max(),
+            Queue::Capped(queue) => queue.best(),
+        }
+    }
+
+    /// The branch with the highest lower bound. Only a [`Queue::Capped`] needs this.
+    fn worst(&self) -> Option<&Branch<'a, S>> {
+        match self {
+            Queue::BestFirst(heap) => heap.iter().min(),
+            Queue::DepthFirst(stack) => stack.iter().min(),
+            Queue::Capped(queue) => queue.worst(),
+        }
+    }
+
+    /// Removes the branch with the highest lower bound. Must not be empty.
+    fn remove_worst(&mut self) -> Branch<'a, S> {
+        match self {
+            Queue::Capped(queue) => queue.remove_worst(),
+            _ => unreachable!("only a capped queue removes its worst branch"),
+        }
+    }
+}
+
+/// Queued branches indexed both by the order they were queued in and by how good they are, so that
+/// the next, best and worst branch can all be found and removed in O(log n).
+#[derive(Debug)]
+struct CappedQueue<'a, S> {
+    exploration: Exploration,
+    /// The queued branches by the order they were queued in.
+    branches: BTreeMap<u64, Branch<'a, S>>,
+    /// The queued branches from worst to best (see [`Branch::priority`]), with the order they were
+    /// queued in to tell equally good branches apart.
+    by_priority: BTreeSet<(Reverse<S>, bool, u64)>,
+    next_seq: u64,
+}
+
+impl<'a, S: Ord + Copy> CappedQueue<'a, S> {
+    fn new(exploration: Exploration) -> Self {
+        CappedQueue {
+            exploration,
+            branches: BTreeMap::new(),
+            by_priority: BTreeSet::new(),
+            next_seq: 0,
+        }
+    }
+
+    fn push(&mut self, branch: Branch<'a, S>) {
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        let (lower_bound, is_inclusion) = branch.priority();
+        self.by_priority.insert((lower_bound, is_inclusion, seq));
+        self.branches.insert(seq, branch);
+    }
+
+    fn pop(&mut self) -> Option<Branch<'a, S>> {
+        let seq = match self.exploration {
+            Exploration::BestFirst => self.by_priority.iter().next_back()?.2,
+            Exploration::DepthFirst => *self.branches.keys().next_back()?,
+        };
+        Some(self.remove(seq))
+    }
+
+    fn best(&self) -> Option<&Branch<'a, S>> {
+        let &(_, _, seq) = self.by_priority.iter().next_back()?;
+        self.branches.get(&seq)
+    }
+
+    fn worst(&self) -> Option<&Branch<'a, S>> {
+        let &(_, _, seq) = self.by_priority.iter().next()?;
+        self.branches.get(&seq)
+    }
+
+    fn remove_worst(&mut self) -> Branch<'a, S> {
+        let &(_, _, seq) = self.by_priority.iter().next().expect("must not be empty");
+        self.remove(seq)
+    }
+
+    fn remove(&mut self, seq: u64) -> Branch<'a, S> {
+        let branch = self.branches.remove(&seq).expect("queued");
+        let (lower_bound, is_inclusion) = branch.priority();
+        self.by_priority.remove(&(lower_bound, is_inclusion, seq));
+        branch
+    }
+}
+
+/// A branch and bound metric where we minimize the [`Score`](Self::Score).
 ///
 /// This is to be used as input for [`CoinSelector::run_bnb`] or [`CoinSelector::bnb_solutions`].
 pub trait BnbMetric {
+    /// The type of the score that is minimized.
+    ///
+    /// The built-in metrics use [`Ordf32`]. An integer like `u64` compares fees exactly whatever
+    /// their size, and a tuple compares several scores lexicographically.
+    type Score: Ord + Copy + core::fmt::Debug;
+
     /// Get the score of a given selection for `target`.
     ///
     /// If this returns `None`, the selection is invalid.
-    fn score(&mut self, cs: &CoinSelector<'_>, target: Target) -> Option<Ordf32>;
+    fn score(&mut self, cs: &CoinSelector<'_>, target: Target) -> Option<Self::Score>;
 
     /// Get the lower bound score using a heuristic for `target`.
     ///
@@ -214,7 +552,7 @@ pub trait BnbMetric {
     ///
     /// If this returns `None`, the current branch and all descendant branches will not have valid
     /// solutions.
-    fn bound(&mut self, cs: &CoinSelector<'_>, target: Target) -> Option<Ordf32>;
+    fn bound(&mut self, cs: &CoinSelector<'_>, target: Target) -> Option<Self::Score>;
 
     /// The change output (a.k.a. drain) this metric decides on for the given selection and `target`,
     /// or [`Drain::NONE`] if it decides there should be no change.
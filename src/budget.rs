@@ -0,0 +1,162 @@
+use core::sync::atomic::{AtomicBool, Ordering};
+
+/// How far a branch and bound search has got. This is what a [`BnbBudget`] is checked against.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BnbProgress {
+    /// The number of rounds done so far. Each round takes one branch off the queue.
+    pub rounds: usize,
+    /// The number of selections that have been bounded by the metric so far. Each round bounds at
+    /// most two.
+    pub nodes: usize,
+}
+
+/// Which limit of a [`BnbBudget`] stopped the search.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BudgetLimit {
+    /// The maximum number of rounds was reached.
+    Rounds(usize),
+    /// The maximum number of nodes (see [`MaxNodes`]) was reached.
+    Nodes(usize),
+    /// The [`Deadline`] passed.
+    Deadline,
+    /// The search was cancelled by a [`StopWhen`] callback or an [`AtomicBool`] flag.
+    Cancelled,
+    /// Branches were dropped because the queue was full (see [`BnbOptions::max_queue_len`]), so
+    /// the search couldn't rule out that there is a solution.
+    ///
+    /// [`BnbOptions::max_queue_len`]: crate::BnbOptions::max_queue_len
+    QueueLen(usize),
+}
+
+// Allow this for now due to MSRV
+#[allow(clippy::uninlined_format_args)]
+impl core::fmt::Display for BudgetLimit {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            BudgetLimit::Rounds(max_rounds) => write!(f, "max rounds ({})", max_rounds),
+            BudgetLimit::Nodes(max_nodes) => write!(f, "max nodes ({})", max_nodes),
+            BudgetLimit::Deadline => write!(f, "deadline"),
+            BudgetLimit::Cancelled => write!(f, "cancelled"),
+            BudgetLimit::QueueLen(max_queue_len) => {
+                write!(f, "max queue length ({})", max_queue_len)
+            }
+        }
+    }
+}
+
+/// Decides when branch and bound has to stop searching, e.g. when it is taking too long.
+///
+/// It is checked before every round of [`CoinSelector::run_bnb`] (but not when the search has
+/// finished anyway). The best solution found so far is still returned if the search is stopped
+/// early.
+///
+/// A budget can be:
+///
+/// - a `usize`: the maximum number of rounds.
+/// - [`MaxNodes`]: the maximum number of selections the metric bounds.
+/// - [`StopWhen`]: a callback that decides to stop.
+/// - a `&AtomicBool`: a cancellation flag, which stops the search once it is `true`.
+/// - [`Deadline`]: a wall-clock deadline (requires the `std` feature).
+/// - a tuple of budgets, which stops as soon as any of them does. `()` never stops.
+///
+/// [`CoinSelector::run_bnb`]: crate::CoinSelector::run_bnb
+pub trait BnbBudget {
+    /// Returns the limit that has been reached given the `progress` of the search, or `None` to
+    /// keep searching.
+    fn check(&mut self, progress: BnbProgress) -> Option<BudgetLimit>;
+}
+
+impl BnbBudget for usize {
+    fn check(&mut self, progress: BnbProgress) -> Option<BudgetLimit> {
+        if progress.rounds >= *self {
+            return Some(BudgetLimit::Rounds(*self));
+        }
+        None
+    }
+}
+
+/// A [`BnbBudget`] that limits the number of selections bounded by the metric. This tracks the work
+/// done more closely than the number of rounds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MaxNodes(pub usize);
+
+impl BnbBudget for MaxNodes {
+    fn check(&mut self, progress: BnbProgress) -> Option<BudgetLimit> {
+        if progress.nodes >= self.0 {
+            return Some(BudgetLimit::Nodes(self.0));
+        }
+        None
+    }
+}
+
+/// A [`BnbBudget`] that stops the search once the callback returns `true`.
+#[derive(Debug, Clone, Copy)]
+pub struct StopWhen<F>(pub F);
+
+impl<F: FnMut() -> bool> BnbBudget for StopWhen<F> {
+    fn check(&mut self, _progress: BnbProgress) -> Option<BudgetLimit> {
+        if (self.0)() {
+            return Some(BudgetLimit::Cancelled);
+        }
+        None
+    }
+}
+
+impl BnbBudget for &AtomicBool {
+    fn check(&mut self, _progress: BnbProgress) -> Option<BudgetLimit> {
+        if self.load(Ordering::Relaxed) {
+            return Some(BudgetLimit::Cancelled);
+        }
+        None
+    }
+}
+
+/// A [`BnbBudget`] that stops the search once the [`Instant`](std::time::Instant) has passed.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Deadline(pub std::time::Instant);
+
+#[cfg(feature = "std")]
+impl Deadline {
+    /// A deadline `duration` from now.
+    pub fn after(duration: std::time::Duration) -> Self {
+        Deadline(std::time::Instant::now() + duration)
+    }
+}
+
+#[cfg(feature = "std")]
+impl BnbBudget for Deadline {
+    fn check(&mut self, _progress: BnbProgress) -> Option<BudgetLimit> {
+        if std::time::Instant::now() >= self.0 {
+            return Some(BudgetLimit::Deadline);
+        }
+        None
+    }
+}
+
+impl<B: BnbBudget + ?Sized> BnbBudget for &mut B {
+    fn check(&mut self, progress: BnbProgress) -> Option<BudgetLimit> {
+        (**self).check(progress)
+    }
+}
+
+impl BnbBudget for () {
+    fn check(&mut self, _progress: BnbProgress) -> Option<BudgetLimit> {
+        None
+    }
+}
+
+impl<A: BnbBudget, B: BnbBudget> BnbBudget for (A, B) {
+    fn check(&mut self, progress: BnbProgress) -> Option<BudgetLimit> {
+        self.0.check(progress).or_else(|| self.1.check(progress))
+    }
+}
+
+impl<A: BnbBudget, B: BnbBudget, C: BnbBudget> BnbBudget for (A, B, C) {
+    fn check(&mut self, progress: BnbProgress) -> Option<BudgetLimit> {
+        self.0
+            .check(progress)
+            .or_else(|| self.1.check(progress))
+            .or_else(|| self.2.check(progress))
+    }
+}
@@ -1,7 +1,8 @@
 use super::*;
-#[allow(unused)] // some bug in <= 1.48.0 sees this as unused when it isn't
-use crate::float::FloatExt;
-use crate::{bitset::Bitset, bnb::BnbMetric, float::Ordf32, ChangePolicy, FeeRate, Target};
+use crate::{
+    bitset::Bitset, bnb::BnbMetric, float::Ordf32, BnbBudget, BnbObserver, BnbOptions, BnbProgress,
+    BudgetLimit, ChangePolicy, FeeRate, Target,
+};
 use alloc::{sync::Arc, vec::Vec};
 
 /// [`CoinSelector`] selects/deselects coins from a set of canididate coins.
@@ -16,6 +17,7 @@ pub struct CoinSelector<'a> {
     candidates: &'a [Candidate],
     selected: Bitset,
     banned: Bitset,
+    conflicts: Bitset,
     candidate_order: Arc<Vec<usize>>,
 }
 
@@ -36,6 +38,7 @@ impl<'a> CoinSelector<'a> {
             candidates,
             selected: Bitset::with_capacity(candidates.len()),
             banned: Bitset::with_capacity(candidates.len()),
+            conflicts: Bitset::with_capacity(candidates.len()),
             candidate_order: Arc::new((0..candidates.len()).collect::<Vec<_>>()),
         }
     }
@@ -105,6 +108,51 @@ impl<'a> CoinSelector<'a> {
         &self.banned
     }
 
+    /// Require the selection to spend the input at `index` or one of the other conflicts added with
+    /// this method.
+    ///
+    /// When replacing a transaction, add the candidates that spend its inputs as conflicts so that
+    /// the replacement actually conflicts with it ([`Replace`] only covers the fee). Branch and
+    /// bound only returns selections that are [`is_conflicting`].
+    ///
+    /// `index` refers to its position in the original `candidates` slice passed into [`CoinSelector::new`].
+    ///
+    /// [`is_conflicting`]: Self::is_conflicting
+    pub fn add_conflict(&mut self, index: usize) {
+        assert!(index < self.candidates.len());
+        self.conflicts.insert(index);
+    }
+
+    /// Gets the list of inputs that have been added by [`add_conflict`].
+    ///
+    /// [`add_conflict`]: Self::add_conflict
+    pub fn conflicts(&self) -> &Bitset {
+        &self.conflicts
+    }
+
+    /// Whether at least one of the [`conflicts`] is selected. Always `true` if there are none.
+    ///
+    /// This is **monotone**: selecting more never un-meets it.
+    ///
+    /// [`conflicts`]: Self::conflicts
+    pub fn is_conflicting(&self) -> bool {
+        self.conflicts.is_empty() || self.conflicts.iter().any(|index| self.is_selected(index))
+    }
+
+    /// Whether this selection or one that adds more candidates to it can be [`is_conflicting`],
+    /// i.e. not every one of the [`conflicts`] has been [`ban`]ned.
+    ///
+    /// [`is_conflicting`]: Self::is_conflicting
+    /// [`conflicts`]: Self::conflicts
+    /// [`ban`]: Self::ban
+    pub fn can_conflict(&self) -> bool {
+        self.is_conflicting()
+            || self
+                .conflicts
+                .iter()
+                .any(|index| !self.banned.contains(index))
+    }
+
     /// Is the input at `index` selected. `index` refers to its position in the original
     /// `candidates` slice passed into [`CoinSelector::new`].
     pub fn is_selected(&self, index: usize) -> bool {
@@ -135,14 +183,18 @@ impl<'a> CoinSelector<'a> {
         self.selected.is_empty()
     }
 
+    /// The number of inputs of the selected candidates (see [`Candidate::input_count`]).
+    pub fn input_count(&self) -> usize {
+        self.selected().map(|(_, wv)| wv.input_count).sum()
+    }
+
     /// The weight of the inputs including the witness header and the varint for the number of
     /// inputs.
     pub fn input_weight(&self) -> u64 {
         let is_segwit_tx = self.selected().any(|(_, wv)| wv.is_segwit);
         let witness_header_extra_weight = is_segwit_tx as u64 * 2;
 
-        let input_count = self.selected().map(|(_, wv)| wv.input_count).sum::<usize>();
-        let input_varint_weight = varint_size(input_count) * 4;
+        let input_varint_weight = varint_size(self.input_count()) * 4;
 
         let selected_weight: u64 = self
             .selected()
@@ -200,6 +252,8 @@ impl<'a> CoinSelector<'a> {
 
     /// How much the current selection overshoots the value need to satisfy `target.fee.rate` and
     /// `target.value` (while ignoring `target.fee.absolute`).
+    ///
+    /// This includes the [`bump_fee`](Self::bump_fee) of the selection at `target.fee.rate`.
     pub fn rate_excess(&self, target: Target, drain: Drain) -> i64 {
         self.selected_value() as i64
             - target.value() as i64
@@ -263,13 +317,14 @@ impl<'a> CoinSelector<'a> {
         if numerator < 0 || denom == 0 {
             return None;
         }
-        Some(FeeRate::from_sat_per_wu(numerator as f32 / denom as f32))
+        Some(FeeRate::from_wu(numerator as u64, denom as usize))
     }
 
     /// The fee the current selection and `drain_weight` should pay to satisfy `target_fee`.
     ///
-    /// This compares the fee calculated from the target feerate with the fee calculated from the
-    /// [`Replace`] constraints and returns the larger of the two.
+    /// This compares the fee calculated from the target feerate (including the
+    /// [`bump_fee`](Self::bump_fee) of the selection) with the fee calculated from the [`Replace`]
+    /// constraints and returns the larger of the two.
     ///
     /// `drain_weight` can be 0 to indicate no draining output.
     pub fn implied_fee(&self, target: Target, drain_weights: DrainWeights) -> u64 {
@@ -292,6 +347,7 @@ impl<'a> CoinSelector<'a> {
             .fee
             .rate
             .implied_fee(self.weight(target.outputs, drain_weights))
+            + self.bump_fee(target.fee.rate)
     }
 
     fn implied_fee_from_feerate_wu(&self, target: Target, drain_weights: DrainWeights) -> u64 {
@@ -299,6 +355,14 @@ impl<'a> CoinSelector<'a> {
             .fee
             .rate
             .implied_fee_wu(self.weight(target.outputs, drain_weights))
+            + self.bump_fee(target.fee.rate)
+    }
+
+    /// The fee needed to bump the unconfirmed ancestors of the selected candidates to `feerate`.
+    ///
+    /// See [`Ancestors::bump_fee`].
+    pub fn bump_fee(&self, feerate: FeeRate) -> u64 {
+        self.selected().map(|(_, c)| c.bump_fee(feerate)).sum()
     }
 
     /// The actual fee the selection would pay if it was used in a transaction that had
@@ -310,13 +374,18 @@ impl<'a> CoinSelector<'a> {
     }
 
     /// The value of the current selected inputs minus the fee needed to pay for the selected inputs
+    /// (including their [`bump_fee`](Self::bump_fee)).
     pub fn effective_value(&self, feerate: FeeRate) -> i64 {
-        self.selected_value() as i64 - (self.input_weight() as f32 * feerate.spwu()).ceil() as i64
+        self.selected_value() as i64
+            - feerate.implied_fee_wu(self.input_weight()) as i64
+            - self.bump_fee(feerate) as i64
     }
 
     // /// Waste sum of all selected inputs.
     fn input_waste(&self, feerate: FeeRate, long_term_feerate: FeeRate) -> f32 {
+        // bumping the ancestors is only ever paid for now so all of it is waste
         self.input_weight() as f32 * (feerate.spwu() - long_term_feerate.spwu())
+            + self.bump_fee(feerate) as f32
     }
 
     /// Sorts the candidates by the comparision function.
@@ -356,8 +425,11 @@ impl<'a> CoinSelector<'a> {
 
     /// Sorts the candidates by descending value per weight unit, tie-breaking with value.
     pub fn sort_candidates_by_descending_value_pwu(&mut self) {
-        self.sort_candidates_by_key(|(_, wv)| {
-            core::cmp::Reverse((Ordf32(wv.value_pwu()), wv.value))
+        // compare `a.value / a.weight` with `b.value / b.weight` exactly by cross-multiplying
+        self.sort_candidates_by(|(_, a), (_, b)| {
+            let a_value_pwu = a.value as u128 * b.weight as u128;
+            let b_value_pwu = b.value as u128 * a.weight as u128;
+            (b_value_pwu, b.value).cmp(&(a_value_pwu, a.value))
         });
     }
 
@@ -365,6 +437,8 @@ impl<'a> CoinSelector<'a> {
     ///
     /// You can pass in an `excess_discount` which must be between `0.0..1.0`. Passing in `1.0` gives you no discount
     ///
+    /// Like Bitcoin Core, the [`bump_fee`](Self::bump_fee) of the selection counts as waste.
+    ///
     /// [waste metric]: https://bitcoin.stackexchange.com/questions/113622/what-does-waste-metric-mean-in-the-context-of-coin-selection
     pub fn waste(
         &self,
@@ -446,19 +520,118 @@ impl<'a> CoinSelector<'a> {
         self.unselected_indices().next().is_none()
     }
 
+    /// The most weight the tx implied by the current selection may have: [`Target::max_weight`] or
+    /// the TRUC limit if [`Target::truc`] is set, whichever is lower.
+    ///
+    /// The TRUC limit is [`TRUC_CHILD_MAX_WEIGHT`] if an unconfirmed candidate is selected and
+    /// [`TRUC_MAX_WEIGHT`] otherwise, so selecting more can only lower the limit.
+    pub fn weight_limit(&self, target: Target) -> Option<u64> {
+        let truc_limit = if !target.truc {
+            None
+        } else if self.selected().any(|(_, c)| c.ancestors.is_some()) {
+            Some(TRUC_CHILD_MAX_WEIGHT)
+        } else {
+            Some(TRUC_MAX_WEIGHT)
+        };
+        match (target.max_weight, truc_limit) {
+            (Some(max_weight), Some(truc_limit)) => Some(max_weight.min(truc_limit)),
+            (max_weight, truc_limit) => max_weight.or(truc_limit),
+        }
+    }
+
     /// Whether the tx implied by the current selection plus a drain of `drain_weights` is within
-    /// [`Target::max_weight`]. Pass [`DrainWeights::NONE`] for a changeless tx.
+    /// the [`weight_limit`](Self::weight_limit), i.e. [`Target::max_weight`] and the TRUC limits.
+    /// Pass [`DrainWeights::NONE`] for a changeless tx.
     ///
-    /// Always `true` when `max_weight` is `None`. Note this is the *anti-monotone* half of
+    /// Always `true` when there's no limit. Note this is the *anti-monotone* half of
     /// feasibility (adding inputs adds weight), so it is kept separate from the monotone
     /// value-only [`is_funded`](Self::is_funded).
     pub fn is_within_max_weight(&self, target: Target, drain_weights: DrainWeights) -> bool {
-        match target.max_weight {
+        match self.weight_limit(target) {
             Some(max_weight) => self.weight(target.outputs, drain_weights) <= max_weight,
             None => true,
         }
     }
 
+    /// Whether the [`input_count`](Self::input_count) is within [`Target::min_input_count`] and
+    /// [`Target::max_input_count`].
+    ///
+    /// Always `true` when neither is set. Like the weight cap, `max_input_count` is *anti-monotone*
+    /// (adding inputs can break it) while `min_input_count` is monotone, so neither is part of
+    /// [`is_funded`](Self::is_funded).
+    pub fn is_within_input_count(&self, target: Target) -> bool {
+        let input_count = self.input_count();
+        target
+            .min_input_count
+            .map_or(true, |min| input_count >= min)
+            && target
+                .max_input_count
+                .map_or(true, |max| input_count <= max)
+    }
+
+    /// Whether the unconfirmed candidates selected can be spent together by the transaction of
+    /// `target` under the TRUC (BIP-431) policy.
+    ///
+    /// A TRUC transaction ([`Target::truc`]) can spend at most one unconfirmed candidate, and only
+    /// if its [`Ancestors::is_truc`]. Any other transaction can't spend unconfirmed TRUC outputs.
+    /// Candidates that spend outputs of the same unconfirmed parent should be grouped into one
+    /// candidate. This is *anti-monotone* like the weight limit: once broken, selecting more never
+    /// fixes it.
+    pub fn is_truc_compatible(&self, target: Target) -> bool {
+        let mut unconfirmed = self.selected().filter_map(|(_, c)| c.ancestors);
+        if target.truc {
+            match (unconfirmed.next(), unconfirmed.next()) {
+                (None, _) => true,
+                (Some(ancestors), None) => ancestors.is_truc,
+                (Some(_), Some(_)) => false,
+            }
+        } else {
+            unconfirmed.all(|ancestors| !ancestors.is_truc)
+        }
+    }
+
+    /// Whether the target value can be covered without breaking the TRUC policy (see
+    /// [`is_truc_compatible`](Self::is_truc_compatible)), ignoring the weight limit.
+    fn is_fundable_within_truc_policy(&self, target: Target) -> bool {
+        if !self.is_truc_compatible(target) {
+            return false;
+        }
+        let rate = target.fee.rate;
+        let mut test = self.clone();
+        let mut truc_unconfirmed = Vec::new();
+        for (index, candidate) in self.unselected() {
+            if candidate.effective_value_sats(rate) <= 0 {
+                continue;
+            }
+            match candidate.ancestors {
+                None => {
+                    test.select(index);
+                }
+                Some(ancestors) if !ancestors.is_truc && !target.truc => {
+                    test.select(index);
+                }
+                Some(ancestors) if ancestors.is_truc && target.truc => {
+                    truc_unconfirmed.push((index, candidate));
+                }
+                Some(_) => {}
+            }
+        }
+        if test.is_funded(target) {
+            return true;
+        }
+        // a TRUC transaction can add the unconfirmed candidate worth the most if it has none yet
+        let best = truc_unconfirmed
+            .into_iter()
+            .max_by_key(|(_, candidate)| candidate.effective_value_sats(rate));
+        match best {
+            Some((index, _)) if test.is_truc_compatible(target) => {
+                test.select(index);
+                test.is_funded(target)
+            }
+            _ => false,
+        }
+    }
+
     /// Whether the selection covers the target value (i.e. [`excess`](Self::excess) is
     /// non-negative), ignoring [`Target::max_weight`].
     ///
@@ -549,7 +722,7 @@ impl<'a> CoinSelector<'a> {
             let cand_index = self.candidate_order[i];
             if self.selected.contains(cand_index)
                 || self.banned.contains(cand_index)
-                || self.candidates[cand_index].effective_value(feerate) <= 0.0
+                || self.candidates[cand_index].effective_value_sats(feerate) <= 0
             {
                 continue;
             }
@@ -559,22 +732,41 @@ impl<'a> CoinSelector<'a> {
 
     /// Select candidates until `target` has been met.
     ///
+    /// Candidates keep being selected after the value is met until there are at least
+    /// [`Target::min_input_count`] inputs.
+    ///
     /// # Errors
     ///
     /// - [`SelectError::InsufficientFunds`] if the candidates can't cover the target value.
     /// - [`SelectError::MaxWeightExceeded`] if the value is met but the resulting selection exceeds
     ///   [`Target::max_weight`]. Note this only reflects *this* in-order greedy selection; a
     ///   different selection might still fit the cap (use branch and bound to search for one).
+    /// - [`SelectError::InputCountOutOfRange`] if the value is met but the resulting selection has
+    ///   fewer inputs than [`Target::min_input_count`] (because the candidates ran out) or more
+    ///   than [`Target::max_input_count`]. Like the weight cap, this only reflects this selection.
+    /// - [`SelectError::TrucPolicyViolated`] if the value is met but the resulting selection spends
+    ///   unconfirmed candidates the TRUC policy doesn't allow (see
+    ///   [`is_truc_compatible`](Self::is_truc_compatible)).
     pub fn select_until_target_met(&mut self, target: Target) -> Result<(), SelectError> {
-        self.select_until(|cs| cs.is_funded(target))
+        let min_input_count = target.min_input_count.unwrap_or(0);
+        self.select_until(|cs| cs.is_funded(target) && cs.input_count() >= min_input_count)
             .ok_or_else(|| {
+                if self.is_funded(target) {
+                    return SelectError::InputCountOutOfRange;
+                }
                 SelectError::InsufficientFunds(InsufficientFunds {
                     missing: self.excess(target, Drain::NONE).unsigned_abs(),
                 })
             })?;
+        if !self.is_truc_compatible(target) {
+            return Err(SelectError::TrucPolicyViolated);
+        }
         if !self.is_within_max_weight(target, DrainWeights::NONE) {
             return Err(SelectError::MaxWeightExceeded);
         }
+        if !self.is_within_input_count(target) {
+            return Err(SelectError::InputCountOutOfRange);
+        }
         Ok(())
     }
 
@@ -611,49 +803,243 @@ impl<'a> CoinSelector<'a> {
         &self,
         target: Target,
         metric: M,
-    ) -> impl Iterator<Item = Option<(CoinSelector<'a>, Ordf32)>> {
-        crate::bnb::BnbIter::new(self.clone(), target, metric)
+    ) -> impl Iterator<Item = Option<(CoinSelector<'a>, M::Score)>> {
+        self.bnb_solutions_with(target, metric, BnbOptions::new((), ()))
+    }
+
+    /// Same as [`bnb_solutions`] but with [`BnbOptions`]. The iterator also ends if the budget of
+    /// the `options` runs out.
+    ///
+    /// [`bnb_solutions`]: Self::bnb_solutions
+    pub fn bnb_solutions_with<M: BnbMetric, B: BnbBudget, O: BnbObserver<M::Score>>(
+        &self,
+        target: Target,
+        metric: M,
+        options: BnbOptions<B, O, M::Score>,
+    ) -> impl Iterator<Item = Option<(CoinSelector<'a>, M::Score)>> {
+        crate::bnb::BnbIter::new(self.clone(), target, metric, options)
     }
 
     /// Run branch and bound to minimize the score of the provided [`BnbMetric`].
     ///
-    /// The method keeps trying until no better solution can be found, or we reach `max_rounds`. If a
-    /// solution is found, the score and the change output ([`Drain`]) that the metric decided on are
-    /// returned. Otherwise, we error with [`NoBnbSolution`].
+    /// The method keeps trying until no better solution can be found, or the `budget` runs out. A
+    /// budget can be a maximum number of rounds (a `usize`), a deadline, a cancellation flag and
+    /// more (see [`BnbBudget`]). If a solution is found, the score and the change output ([`Drain`])
+    /// that the metric decided on are returned. Otherwise, we error with [`NoBnbSolution`].
     ///
-    /// Use [`CoinSelector::bnb_solutions`] to access the branch and bound iterator directly.
-    pub fn run_bnb<M: BnbMetric>(
+    /// Use [`CoinSelector::bnb_solutions`] to access the branch and bound iterator directly, or
+    /// [`CoinSelector::run_bnb_with`] to observe the search or find out how close to the best
+    /// solution the one returned is.
+    pub fn run_bnb<M: BnbMetric, B: BnbBudget>(
         &mut self,
         target: Target,
         metric: M,
-        max_rounds: usize,
-    ) -> Result<(Ordf32, Drain), NoBnbSolution> {
-        let mut iter = crate::bnb::BnbIter::new(self.clone(), target, metric);
-        let mut rounds = 0_usize;
-        let best = iter
-            .by_ref()
-            .take(max_rounds)
-            .inspect(|_| rounds += 1)
-            .flatten()
-            .last();
+        budget: B,
+    ) -> Result<(M::Score, Drain), NoBnbSolution> {
+        self.run_bnb_with(target, metric, BnbOptions::new(budget, ()))
+            .map(|solution| (solution.score, solution.drain))
+    }
+
+    /// Same as [`run_bnb`] but with [`BnbOptions`], e.g. to collect [`BnbStats`] about the search.
+    ///
+    /// The selection is returned as a [`BnbSolution`], which also says whether the search proved
+    /// that there is no better one, and if not, how much better one could be (see
+    /// [`BnbSolution::lower_bound`]).
+    ///
+    /// [`run_bnb`]: Self::run_bnb
+    /// [`BnbStats`]: crate::BnbStats
+    pub fn run_bnb_with<M: BnbMetric, B: BnbBudget, O: BnbObserver<M::Score>>(
+        &mut self,
+        target: Target,
+        metric: M,
+        options: BnbOptions<B, O, M::Score>,
+    ) -> Result<BnbSolution<'a, M::Score>, NoBnbSolution> {
+        let upper_bound = options.upper_bound;
+        let mut iter = crate::bnb::BnbIter::new(self.clone(), target, metric, options);
+        let best = iter.by_ref().flatten().last();
         if let Some((selector, score)) = best {
-            let drain = iter.metric.drain(&selector, target);
-            *self = selector;
-            return Ok((score, drain));
+            let solution = iter.solution(selector, score);
+            *self = solution.selector.clone();
+            return Ok(solution);
         }
 
-        // No solution. If the iterator still has an item we stopped at the round limit and a
-        // solution may still exist with a larger `max_rounds`. Otherwise the tree was fully
-        // explored, so no selection satisfies the target — a genuine infeasibility, split into
-        // value vs weight.
-        if iter.next().is_some() {
-            assert_eq!(rounds, max_rounds); // still-yielding ⟹ we truncated at the cap
-            return Err(NoBnbSolution::RoundLimit { max_rounds, rounds });
+        Err(self.no_bnb_solution(target, &iter, upper_bound))
+    }
+
+    /// Same as [`run_bnb_with`] but returns up to `k` solutions, best first.
+    ///
+    /// Branches are only pruned if they can't beat the `k`th best solution found so far, so this
+    /// takes more rounds than finding just the best. The solutions are all different selections,
+    /// though selections that only differ by candidates that are equal in every field (and are
+    /// either both or neither one of the [`conflicts`](Self::conflicts)) count as the same. Fewer
+    /// than `k` solutions are returned if there aren't `k` of them (or the budget runs out before
+    /// they are found).
+    ///
+    /// The selection of `self` is left as is.
+    ///
+    /// [`run_bnb_with`]: Self::run_bnb_with
+    pub fn run_bnb_top_k<M: BnbMetric, B: BnbBudget, O: BnbObserver<M::Score>>(
+        &self,
+        target: Target,
+        metric: M,
+        options: BnbOptions<B, O, M::Score>,
+        k: usize,
+    ) -> Result<Vec<BnbSolution<'a, M::Score>>, NoBnbSolution> {
+        if k == 0 {
+            return Ok(Vec::new());
+        }
+        let upper_bound = options.upper_bound;
+        let mut iter =
+            crate::bnb::BnbIter::new(self.clone(), target, metric, options).with_top_k(k);
+        let mut top = Vec::<(CoinSelector<'a>, M::Score)>::with_capacity(k + 1);
+        for (selector, score) in iter.by_ref().flatten() {
+            let position = top.partition_point(|&(_, top_score)| top_score <= score);
+            top.insert(position, (selector, score));
+            top.truncate(k);
+        }
+        if top.is_empty() {
+            return Err(self.no_bnb_solution(target, &iter, upper_bound));
+        }
+        Ok(top
+            .into_iter()
+            .map(|(selector, score)| iter.solution(selector, score))
+            .collect())
+    }
+
+    /// Why a branch and bound search that ran to the end of `iter` found no solution.
+    pub(crate) fn no_bnb_solution<M: BnbMetric, B: BnbBudget, O: BnbObserver<M::Score>>(
+        &self,
+        target: Target,
+        iter: &crate::bnb::BnbIter<'a, M, B, O>,
+        upper_bound: Option<M::Score>,
+    ) -> NoBnbSolution {
+        // If the budget ran out, or branches were dropped from a full queue, a solution may still
+        // exist. Otherwise the tree was fully explored, so no selection satisfies the target — a
+        // genuine infeasibility, split into value vs weight.
+        if let Some(limit) = iter.exhausted {
+            return NoBnbSolution::BudgetExhausted {
+                limit,
+                progress: iter.progress,
+            };
         }
         if !self.is_fundable(target) {
-            return Err(NoBnbSolution::InsufficientFunds);
+            return NoBnbSolution::InsufficientFunds;
+        }
+        if !self.can_conflict() {
+            return NoBnbSolution::NoConflict;
+        }
+        if let (true, Some(max_queue_len)) =
+            (iter.dropped_lower_bound.is_some(), iter.max_queue_len)
+        {
+            return NoBnbSolution::BudgetExhausted {
+                limit: BudgetLimit::QueueLen(max_queue_len),
+                progress: iter.progress,
+            };
+        }
+        if upper_bound.is_some() {
+            return NoBnbSolution::NoBetterSolution;
+        }
+        self.exceeded_limit(target)
+    }
+
+    /// Which of the limits of a fundable `target` left branch and bound without a solution: the
+    /// TRUC policy if the value can't be covered without breaking it, the input count if it can't
+    /// be met on its own or if there's no weight limit, and otherwise the weight limit. If none of
+    /// them apply, the metric must have refused every selection.
+    pub(crate) fn exceeded_limit(&self, target: Target) -> NoBnbSolution {
+        if !self.is_fundable_within_truc_policy(target) {
+            return NoBnbSolution::TrucPolicyViolated;
+        }
+        let limits_input_count =
+            target.min_input_count.is_some() || target.max_input_count.is_some();
+        if limits_input_count
+            && (self.weight_limit(target).is_none()
+                || !crate::metrics::can_reach_input_count(self, target))
+        {
+            return NoBnbSolution::InputCountOutOfRange;
+        }
+        if self.weight_limit(target).is_none() {
+            return NoBnbSolution::NoScoredSelection;
+        }
+        NoBnbSolution::MaxWeightExceeded
+    }
+
+    /// Same as [`run_bnb_with`] but starts from an `incumbent` selection, e.g. one from
+    /// [`select_until_target_met`] or a previous transaction.
+    ///
+    /// The `metric` scores the `incumbent` and only selections with a lower score are searched for
+    /// (see [`BnbOptions::upper_bound`]), so the search can prune from the start. If nothing better
+    /// is found, e.g. because the budget runs out, the selection is set to the `incumbent` and it is
+    /// returned. If the metric finds the `incumbent` invalid, this is the same as [`run_bnb_with`].
+    ///
+    /// The `incumbent` must select from the same candidates as `self`.
+    ///
+    /// [`run_bnb_with`]: Self::run_bnb_with
+    /// [`select_until_target_met`]: Self::select_until_target_met
+    pub fn run_bnb_with_incumbent<M: BnbMetric, B: BnbBudget, O: BnbObserver<M::Score>>(
+        &mut self,
+        target: Target,
+        mut metric: M,
+        mut options: BnbOptions<B, O, M::Score>,
+        incumbent: &CoinSelector<'a>,
+    ) -> Result<BnbSolution<'a, M::Score>, NoBnbSolution> {
+        let score = match metric.score(incumbent, target) {
+            Some(score) => score,
+            None => return self.run_bnb_with(target, metric, options),
+        };
+        options.upper_bound = Some(options.upper_bound.map_or(score, |bound| bound.min(score)));
+        let mut iter = crate::bnb::BnbIter::new(self.clone(), target, metric, options);
+        let (selector, score) = iter
+            .by_ref()
+            .flatten()
+            .last()
+            .unwrap_or_else(|| (incumbent.clone(), score));
+        let solution = iter.solution(selector, score);
+        *self = solution.selector.clone();
+        Ok(solution)
+    }
+}
+
+/// A [`CoinSelector`] without its candidates, so that it can be sent to another thread.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone)]
+pub(crate) struct DetachedSelector {
+    selected: Bitset,
+    banned: Bitset,
+    conflicts: Bitset,
+    candidate_order: Arc<Vec<usize>>,
+}
+
+#[cfg(feature = "std")]
+impl CoinSelector<'_> {
+    pub(crate) fn detach(&self) -> DetachedSelector {
+        DetachedSelector {
+            selected: self.selected.clone(),
+            banned: self.banned.clone(),
+            conflicts: self.conflicts.clone(),
+            candidate_order: self.candidate_order.clone(),
+        }
+    }
+
+    /// Takes on the selection of `detached`, which must have been detached from a selector with
+    /// the same candidates.
+    pub(crate) fn reattach(&mut self, detached: DetachedSelector) {
+        *self = detached.attach(self.candidates);
+    }
+}
+
+#[cfg(feature = "std")]
+impl DetachedSelector {
+    /// `candidates` must be the same as those of the selector this was detached from.
+    pub(crate) fn attach(self, candidates: &[Candidate]) -> CoinSelector<'_> {
+        debug_assert_eq!(candidates.len(), self.candidate_order.len());
+        CoinSelector {
+            candidates,
+            selected: self.selected,
+            banned: self.banned,
+            conflicts: self.conflicts,
+            candidate_order: self.candidate_order,
         }
-        Err(NoBnbSolution::MaxWeightExceeded)
     }
 }
 
@@ -708,6 +1094,29 @@ impl DoubleEndedIterator for SelectIter<'_> {
     }
 }
 
+/// A solution found by branch and bound, e.g. by [`CoinSelector::run_bnb_with`].
+///
+/// `S` is the [`BnbMetric::Score`] of the metric.
+#[derive(Debug, Clone)]
+pub struct BnbSolution<'a, S = Ordf32> {
+    /// The selection.
+    pub selector: CoinSelector<'a>,
+    /// The score the metric gave the selection.
+    pub score: S,
+    /// The change output the metric decided on for the selection.
+    pub drain: Drain,
+    /// No selection the search didn't find scores lower than this.
+    ///
+    /// It's the lowest lower bound of the branches the search didn't get to (because the budget
+    /// ran out or the queue was full), or `score` if that is lower. So `score - lower_bound` is
+    /// the most the best solution could improve on this one.
+    pub lower_bound: S,
+    /// Whether the search proved that no selection it didn't find scores lower than `score`, e.g.
+    /// because it searched every branch that could. For the best solution this means it is
+    /// optimal.
+    pub proven_optimal: bool,
+}
+
 /// Error type that occurs when the target amount cannot be met.
 #[derive(Clone, Debug, Copy, PartialEq, Eq)]
 pub struct InsufficientFunds {
@@ -731,6 +1140,12 @@ pub enum SelectError {
     InsufficientFunds(InsufficientFunds),
     /// The value target is met, but the resulting selection exceeds [`Target::max_weight`].
     MaxWeightExceeded,
+    /// The value target is met, but the resulting selection has fewer inputs than
+    /// [`Target::min_input_count`] or more than [`Target::max_input_count`].
+    InputCountOutOfRange,
+    /// The value target is met, but the resulting selection spends unconfirmed candidates that the
+    /// TRUC policy doesn't allow (see [`CoinSelector::is_truc_compatible`]).
+    TrucPolicyViolated,
 }
 
 impl From<InsufficientFunds> for SelectError {
@@ -749,6 +1164,18 @@ impl core::fmt::Display for SelectError {
                     "Selection meets the target value but exceeds `max_weight`."
                 )
             }
+            SelectError::InputCountOutOfRange => {
+                write!(
+                    f,
+                    "Selection meets the target value but has too few or too many inputs."
+                )
+            }
+            SelectError::TrucPolicyViolated => {
+                write!(
+                    f,
+                    "Selection meets the target value but spends unconfirmed outputs against TRUC policy."
+                )
+            }
         }
     }
 }
@@ -769,14 +1196,36 @@ pub enum NoBnbSolution {
     ///
     /// [`LowestFee`]: crate::metrics::LowestFee
     MaxWeightExceeded,
-    /// The round limit was reached before the search finished — a solution may still exist with a
-    /// larger `max_rounds`.
-    RoundLimit {
-        /// Maximum rounds set by the caller.
-        max_rounds: usize,
-        /// Number of branch-and-bound rounds performed.
-        rounds: usize,
+    /// The [`BnbBudget`] ran out before the search finished — a solution may still exist with a
+    /// larger budget. This is also returned if branches were dropped because of
+    /// [`BnbOptions::max_queue_len`] (see [`BudgetLimit::QueueLen`]).
+    BudgetExhausted {
+        /// The limit of the budget that was reached.
+        limit: BudgetLimit,
+        /// How far the search got.
+        progress: BnbProgress,
     },
+    /// No selection scores lower than [`BnbOptions::upper_bound`].
+    NoBetterSolution,
+    /// Some selection covers the target value, but every one of them has fewer inputs than
+    /// [`Target::min_input_count`] or more than [`Target::max_input_count`].
+    ///
+    /// If [`Target::max_weight`] is set as well and neither constraint is infeasible on its own,
+    /// [`MaxWeightExceeded`](Self::MaxWeightExceeded) is returned instead.
+    InputCountOutOfRange,
+    /// Every one of the [`conflicts`] is banned, so no selection can conflict with the transaction
+    /// being replaced.
+    ///
+    /// [`conflicts`]: CoinSelector::conflicts
+    NoConflict,
+    /// The candidates can cover the target value, but only by spending unconfirmed candidates that
+    /// the TRUC policy doesn't allow together (see [`CoinSelector::is_truc_compatible`]).
+    TrucPolicyViolated,
+    /// Some selection covers the target value within every limit of the [`Target`], but the metric
+    /// scored none of them, e.g. [`Changeless`] when every such selection needs change.
+    ///
+    /// [`Changeless`]: crate::metrics::Changeless
+    NoScoredSelection,
 }
 
 // Allow this for now due to MSRV
@@ -796,11 +1245,36 @@ impl core::fmt::Display for NoBnbSolution {
                     "no bnb solution: no selection meets the target within max_weight"
                 )
             }
-            NoBnbSolution::RoundLimit { max_rounds, rounds } => write!(
+            NoBnbSolution::BudgetExhausted { limit, progress } => write!(
                 f,
-                "no bnb solution found after {} rounds (max rounds is {})",
-                rounds, max_rounds
+                "no bnb solution found before the budget ran out ({}) after {} rounds and {} nodes",
+                limit, progress.rounds, progress.nodes
             ),
+            NoBnbSolution::NoBetterSolution => write!(
+                f,
+                "no bnb solution: no selection scores lower than the upper bound"
+            ),
+            NoBnbSolution::InputCountOutOfRange => {
+                write!(
+                    f,
+                    "no bnb solution: no selection meets the target with the allowed number of inputs"
+                )
+            }
+            NoBnbSolution::NoConflict => {
+                write!(f, "no bnb solution: every conflict candidate is banned")
+            }
+            NoBnbSolution::TrucPolicyViolated => {
+                write!(
+                    f,
+                    "no bnb solution: no selection meets the target while following TRUC policy"
+                )
+            }
+            NoBnbSolution::NoScoredSelection => {
+                write!(
+                    f,
+                    "no bnb solution: the metric scored none of the selections that meet the target"
+                )
+            }
         }
     }
 }
@@ -811,7 +1285,7 @@ impl std::error::Error for NoBnbSolution {}
 /// A `Candidate` represents an input candidate for [`CoinSelector`].
 ///
 /// This can either be a single UTXO, or a group of UTXOs that should be spent together.
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Candidate {
     /// Total value of the UTXO(s) that this [`Candidate`] represents.
     pub value: u64,
@@ -823,6 +1297,38 @@ pub struct Candidate {
     pub input_count: usize,
     /// Whether this [`Candidate`] contains at least one segwit spend.
     pub is_segwit: bool,
+    /// The unconfirmed ancestors of the UTXO(s), if any.
+    ///
+    /// If they pay a lower feerate than the target, spending this candidate means also paying to
+    /// bump them (CPFP). See [`Ancestors::bump_fee`].
+    pub ancestors: Option<Ancestors>,
+}
+
+/// The unconfirmed transactions a [`Candidate`] depends on.
+///
+/// Miners only include our transaction together with its unconfirmed ancestors, so the package has
+/// to reach the target feerate. If the ancestors pay less than that, the difference is added to
+/// the fee our transaction must pay. This is like the bump fee of Bitcoin Core's `MiniMiner` except
+/// that ancestors shared between candidates are charged for by each of them, which overestimates
+/// the fee.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Ancestors {
+    /// The total fee paid by the unconfirmed ancestors.
+    pub fee: u64,
+    /// The total weight of the unconfirmed ancestors.
+    pub weight: u64,
+    /// Whether the ancestors are a single TRUC (BIP-431, version 3) transaction.
+    ///
+    /// Only a TRUC transaction can spend the outputs of an unconfirmed TRUC transaction, and it can
+    /// only spend them if that is its only unconfirmed parent (see [`Target::truc`]).
+    pub is_truc: bool,
+}
+
+impl Ancestors {
+    /// The fee needed to lift the ancestors to `feerate`, or `0` if they already pay at least that.
+    pub fn bump_fee(&self, feerate: FeeRate) -> u64 {
+        feerate.implied_fee(self.weight).saturating_sub(self.fee)
+    }
 }
 
 impl Candidate {
@@ -843,12 +1349,28 @@ impl Candidate {
             weight,
             input_count: 1,
             is_segwit,
+            ancestors: None,
         }
     }
 
-    /// Effective value of this input candidate: `actual_value - input_weight * feerate (sats/wu)`.
+    /// The fee needed to bump the unconfirmed [`ancestors`] of this candidate to `feerate`.
+    ///
+    /// [`ancestors`]: Self::ancestors
+    pub fn bump_fee(&self, feerate: FeeRate) -> u64 {
+        self.ancestors
+            .map_or(0, |ancestors| ancestors.bump_fee(feerate))
+    }
+
+    /// Effective value of this input candidate: `actual_value - input_weight * feerate (sats/wu)
+    /// - bump_fee`.
     pub fn effective_value(&self, feerate: FeeRate) -> f32 {
-        self.value as f32 - (self.weight as f32 * feerate.spwu())
+        self.value as f32 - (self.weight as f32 * feerate.spwu()) - self.bump_fee(feerate) as f32
+    }
+
+    /// Same as [`effective_value`](Self::effective_value) but in whole satoshis and without
+    /// floats. The fee of the input is rounded up.
+    pub fn effective_value_sats(&self, feerate: FeeRate) -> i64 {
+        self.value as i64 - self.implied_fee_sats(feerate) as i64
     }
 
     /// Value per weight unit
@@ -859,13 +1381,19 @@ impl Candidate {
     /// The amount of *effective value* you receive per weight unit from adding this candidate as an
     /// input.
     pub fn effective_value_pwu(&self, feerate: FeeRate) -> f32 {
-        self.value_pwu() - feerate.spwu()
+        self.effective_value(feerate) / self.weight as f32
     }
 
     /// The (minimum) fee you'd have to pay to add this input to a transaction as implied by the
-    /// `feerate`.
+    /// `feerate`, including the [`bump_fee`](Self::bump_fee) of its ancestors.
     pub fn implied_fee(&self, feerate: FeeRate) -> f32 {
-        self.weight as f32 * feerate.spwu()
+        self.weight as f32 * feerate.spwu() + self.bump_fee(feerate) as f32
+    }
+
+    /// Same as [`implied_fee`](Self::implied_fee) but in whole satoshis and without floats. The
+    /// fee of the input's weight is rounded up.
+    pub fn implied_fee_sats(&self, feerate: FeeRate) -> u64 {
+        feerate.implied_fee_wu(self.weight) + self.bump_fee(feerate)
     }
 
     /// The amount of fee you have to pay per satoshi of value you add from this input.
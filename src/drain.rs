@@ -44,16 +44,32 @@ impl DrainWeights {
         long_term_feerate: FeeRate,
         n_target_outputs: usize,
     ) -> f32 {
+        self.extra_output_weight(n_target_outputs) as f32 * feerate.spwu()
+            + self.spend_weight as f32 * long_term_feerate.spwu()
+    }
+
+    /// Same as [`waste`](Self::waste) but in whole satoshis and without floats. The fees of adding
+    /// and of spending the drain are each rounded up.
+    pub fn waste_sats(
+        &self,
+        feerate: FeeRate,
+        long_term_feerate: FeeRate,
+        n_target_outputs: usize,
+    ) -> u64 {
+        feerate.implied_fee_wu(self.extra_output_weight(n_target_outputs))
+            + long_term_feerate.implied_fee_wu(self.spend_weight)
+    }
+
+    /// The weight of the drain output(s) including the varint of the output count growing.
+    fn extra_output_weight(&self, n_target_outputs: usize) -> u64 {
         let extra_varint_weight =
             (varint_size(n_target_outputs + self.n_outputs) - varint_size(n_target_outputs)) * 4;
-        let extra_output_weight = self.output_weight + extra_varint_weight;
-        extra_output_weight as f32 * feerate.spwu()
-            + self.spend_weight as f32 * long_term_feerate.spwu()
+        self.output_weight + extra_varint_weight
     }
 
     /// The fee you will pay to spend these change output(s) in the future.
     pub fn spend_fee(&self, long_term_feerate: FeeRate) -> u64 {
-        (self.spend_weight as f32 * long_term_feerate.spwu()).ceil() as u64
+        long_term_feerate.implied_fee_wu(self.spend_weight)
     }
 
     /// The minimum value a change output with these weights must have to not be considered dust
@@ -1,34 +1,42 @@
-#[cfg(not(feature = "std"))]
-use crate::float::FloatExt;
-use crate::float::Ordf32;
 use core::ops::{Add, Sub};
 
 /// Fee rate
-#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
-// Internally stored as satoshi/weight unit
-pub struct FeeRate(Ordf32);
+///
+/// Internally this is a whole number of satoshis per 1000 vbytes (sat/kvB) like Bitcoin Core, so
+/// fees are calculated exactly with integers (see [`implied_fee`]). The `f32` constructors and
+/// accessors are only there for convenience.
+///
+/// [`implied_fee`]: Self::implied_fee
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+// Internally stored as satoshi/kvbyte
+pub struct FeeRate(u64);
 
 impl FeeRate {
     /// A feerate of zero
-    pub const ZERO: Self = Self(Ordf32(0.0));
+    pub const ZERO: Self = Self(0);
     /// The default minimum relay fee that bitcoin core uses (1 sat per vbyte). The feerate your transaction has must
     /// be at least this to be forwarded by most nodes on the network.
-    pub const DEFAULT_MIN_RELAY: Self = Self(Ordf32(0.25));
+    pub const DEFAULT_MIN_RELAY: Self = Self(1_000);
     /// The defualt incremental relay fee that bitcoin core uses (1 sat per vbyte). You must pay
     /// this fee over the fee of the transaction(s) you are replacing by through the replace-by-fee
     /// mechanism. This feerate is applied to the transaction that is replacing the old
     /// transactions.
-    pub const DEFUALT_RBF_INCREMENTAL_RELAY: Self = Self(Ordf32(0.25));
-    /// Create a new instance checking the value provided
+    pub const DEFUALT_RBF_INCREMENTAL_RELAY: Self = Self(1_000);
+    /// Create a new instance checking the value provided, rounded to the nearest sat/kvB
     ///
     /// ## Panics
     ///
     /// Panics if the value is not [normal](https://doc.rust-lang.org/std/primitive.f32.html#method.is_normal) (except if it's a positive zero) or negative.
-    fn new_checked(value: f32) -> Self {
-        assert!(value.is_normal() || value == 0.0);
-        assert!(value.is_sign_positive());
+    fn new_checked(sat_per_kvb: f32) -> Self {
+        assert!(sat_per_kvb.is_normal() || sat_per_kvb == 0.0);
+        assert!(sat_per_kvb.is_sign_positive());
 
-        Self(Ordf32(value))
+        Self((sat_per_kvb + 0.5) as u64)
+    }
+
+    /// Create a new instance of [`FeeRate`] given a fee rate in satoshi/kvbyte.
+    pub const fn from_sat_per_kvb(sat_per_kvb: u64) -> Self {
+        Self(sat_per_kvb)
     }
 
     /// Create a new instance of [`FeeRate`] given a float fee rate in btc/kvbytes
@@ -37,7 +45,7 @@ impl FeeRate {
     ///
     /// Panics if the value is not [normal](https://doc.rust-lang.org/std/primitive.f32.html#method.is_normal) (except if it's a positive zero) or negative.
     pub fn from_btc_per_kvb(btc_per_kvb: f32) -> Self {
-        Self::new_checked(btc_per_kvb * 1e5 / 4.0)
+        Self::new_checked(btc_per_kvb * 1e8)
     }
 
     /// Create a new instance of [`FeeRate`] given a float fee rate in satoshi/vbyte
@@ -46,58 +54,74 @@ impl FeeRate {
     ///
     /// Panics if the value is not [normal](https://doc.rust-lang.org/std/primitive.f32.html#method.is_normal) (except if it's a positive zero) or negative.
     pub fn from_sat_per_vb(sat_per_vb: f32) -> Self {
-        Self::new_checked(sat_per_vb / 4.0)
+        Self::new_checked(sat_per_vb * 1e3)
     }
 
     /// Create a new [`FeeRate`] with the default min relay fee value
     #[deprecated(note = "use the DEFAULT_MIN_RELAY constant instead")]
     pub const fn default_min_relay_fee() -> Self {
-        Self(Ordf32(0.25))
+        Self::DEFAULT_MIN_RELAY
     }
 
-    /// Calculate fee rate from `fee` and weight units (`wu`).
+    /// Calculate fee rate from `fee` and weight units (`wu`), rounded down to a whole sat/kvB.
     pub fn from_wu(fee: u64, wu: usize) -> Self {
-        Self::from_sat_per_wu(fee as f32 / wu as f32)
+        Self::from_fee_per_kvb(fee as u128 * 4_000, wu as u128)
     }
 
     /// Calculate feerate from `satoshi/wu`.
     pub fn from_sat_per_wu(sats_per_wu: f32) -> Self {
-        Self::new_checked(sats_per_wu)
+        Self::new_checked(sats_per_wu * 4e3)
     }
 
-    /// Calculate fee rate from `fee` and `vbytes`.
+    /// Calculate fee rate from `fee` and `vbytes`, rounded down to a whole sat/kvB.
     pub fn from_vb(fee: u64, vbytes: usize) -> Self {
-        let rate = fee as f32 / vbytes as f32;
-        Self::from_sat_per_vb(rate)
+        Self::from_fee_per_kvb(fee as u128 * 1_000, vbytes as u128)
+    }
+
+    fn from_fee_per_kvb(fee_times_kvb: u128, size: u128) -> Self {
+        assert!(size > 0, "the size must not be zero");
+        Self((fee_times_kvb / size).min(u64::MAX as u128) as u64)
+    }
+
+    /// Return the value as satoshi/kvbyte.
+    pub const fn as_sat_per_kvb(&self) -> u64 {
+        self.0
     }
 
     /// Return the value as satoshi/vbyte.
     pub fn as_sat_vb(&self) -> f32 {
-        self.0 .0 * 4.0
+        self.0 as f32 / 1e3
     }
 
     /// Return the value as satoshi/wu.
     pub fn spwu(&self) -> f32 {
-        self.0 .0
+        self.0 as f32 / 4e3
     }
 
     /// The fee that the transaction with weight `tx_weight` should pay in order to satisfy the fee rate given by `self`,
     /// where the fee rate is applied to the rounded-up vbytes obtained from `tx_weight`.
+    ///
+    /// The fee is rounded up to a whole satoshi, like Bitcoin Core does.
     pub fn implied_fee(&self, tx_weight: u64) -> u64 {
-        ((tx_weight as f32 / 4.0).ceil() * self.as_sat_vb()).ceil() as u64
+        let vbytes = (tx_weight + 3) / 4;
+        div_ceil(vbytes as u128 * self.0 as u128, 1_000)
     }
 
     /// Same as [implied_fee](Self::implied_fee) except the fee rate given by `self` is applied to `tx_weight` directly.
     pub fn implied_fee_wu(&self, tx_weight: u64) -> u64 {
-        (tx_weight as f32 * self.spwu()).ceil() as u64
+        div_ceil(tx_weight as u128 * self.0 as u128, 4_000)
     }
 }
 
+fn div_ceil(numerator: u128, denominator: u128) -> u64 {
+    ((numerator + denominator - 1) / denominator).min(u64::MAX as u128) as u64
+}
+
 impl Add<FeeRate> for FeeRate {
     type Output = Self;
 
     fn add(self, rhs: FeeRate) -> Self::Output {
-        Self(Ordf32(self.0 .0 + rhs.0 .0))
+        Self(self.0.saturating_add(rhs.0))
     }
 }
 
@@ -105,6 +129,7 @@ impl Sub<FeeRate> for FeeRate {
     type Output = Self;
 
     fn sub(self, rhs: FeeRate) -> Self::Output {
-        Self(Ordf32(self.0 .0 - rhs.0 .0))
+        // a feerate can't be negative
+        Self(self.0.saturating_sub(rhs.0))
     }
 }
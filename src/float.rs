@@ -65,6 +65,8 @@ impl core::fmt::Display for Ordf64 {
 pub trait FloatExt {
     /// Adds the ceil method to `f32`
     fn ceil(self) -> Self;
+    /// Adds the floor method to `f32`
+    fn floor(self) -> Self;
 }
 
 impl FloatExt for f32 {
@@ -79,6 +81,10 @@ impl FloatExt for f32 {
             floored_towards_zero + 1.0
         }
     }
+
+    fn floor(self) -> Self {
+        -FloatExt::ceil(-self)
+    }
 }
 
 #[cfg(test)]
@@ -93,4 +99,14 @@ mod test {
         assert_eq!((1.1).ceil(), 2.0);
         assert_eq!((2.9).ceil(), 3.0);
     }
+
+    #[test]
+    fn floor32() {
+        assert_eq!(FloatExt::floor(-1.1_f32), -2.0);
+        assert_eq!(FloatExt::floor(-0.1_f32), -1.0);
+        assert_eq!(FloatExt::floor(0.0_f32), 0.0);
+        assert_eq!(FloatExt::floor(1.0_f32), 1.0);
+        assert_eq!(FloatExt::floor(1.1_f32), 1.0);
+        assert_eq!(FloatExt::floor(2.9_f32), 2.0);
+    }
 }
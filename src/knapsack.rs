@@ -0,0 +1,187 @@
+use crate::{
+    float::Ordf32, random::shuffle, ChangePolicy, CoinSelector, Drain, InsufficientFunds,
+    RandomSource, SelectError, Target,
+};
+use alloc::vec::Vec;
+
+/// How many rounds of stochastic approximation to run. Same as Bitcoin Core.
+const KNAPSACK_ITERATIONS: usize = 1000;
+
+impl CoinSelector<'_> {
+    /// Select candidates with Bitcoin Core's knapsack solver.
+    ///
+    /// This is a port of Core's `KnapsackSolver`, which it still uses as a fallback. It looks for a
+    /// selection that meets the `target` exactly or else one that leaves at least
+    /// `change_policy.min_value` for change, by randomly trying subsets of the candidates that are
+    /// smaller than that. If the single smallest candidate that is larger does better, it picks
+    /// that instead. This is mainly useful to compare against wallets built on Bitcoin Core.
+    ///
+    /// Rather than comparing raw values like Core, each step checks the actual [`excess`] of the
+    /// selection, so all of the `target`'s fee constraints are respected. Candidates that don't
+    /// pay for themselves at `target.fee.rate` are never selected.
+    ///
+    /// Returns the change output according to `change_policy` (see [`drain`]).
+    ///
+    /// # Errors
+    ///
+    /// - [`SelectError::InsufficientFunds`] if the candidates can't cover the target value.
+    /// - [`SelectError::MaxWeightExceeded`] if the chosen selection exceeds
+    ///   [`Target::max_weight`]. The selection is left unchanged.
+    /// - [`SelectError::TrucPolicyViolated`] if the chosen selection spends unconfirmed candidates
+    ///   the TRUC policy doesn't allow together (see [`is_truc_compatible`]). The selection is left
+    ///   unchanged.
+    ///
+    /// [`excess`]: Self::excess
+    /// [`drain`]: Self::drain
+    /// [`is_truc_compatible`]: Self::is_truc_compatible
+    pub fn select_knapsack<R: RandomSource>(
+        &mut self,
+        target: Target,
+        change_policy: ChangePolicy,
+        rng: &mut R,
+    ) -> Result<Drain, SelectError> {
+        if self.is_funded(target) {
+            return self.finish_knapsack(target, change_policy, &[]);
+        }
+        // Core's "target + change target": enough left over for the smallest change we'd make.
+        let with_change = Drain {
+            weights: change_policy.drain_weights,
+            value: change_policy.min_value,
+        };
+        let excess_with = |cs: &CoinSelector<'_>, index: usize, drain: Drain| {
+            let mut cs = cs.clone();
+            cs.select(index);
+            cs.excess(target, drain)
+        };
+
+        let mut candidates = self
+            .unselected()
+            .filter(|(_, c)| c.effective_value(target.fee.rate) > 0.0)
+            .map(|(index, _)| index)
+            .collect::<Vec<_>>();
+        shuffle(rng, &mut candidates);
+
+        let mut lowest_larger = Option::<(usize, i64)>::None;
+        let mut applicable = Vec::new();
+        for index in candidates {
+            let excess = excess_with(self, index, Drain::NONE);
+            if excess == 0 {
+                return self.finish_knapsack(target, change_policy, &[index]);
+            }
+            if excess_with(self, index, with_change) < 0 {
+                applicable.push(index);
+            } else if lowest_larger.map_or(true, |(_, lowest)| excess < lowest) {
+                lowest_larger = Some((index, excess));
+            }
+        }
+
+        let mut all_lower = self.clone();
+        applicable.iter().for_each(|&index| {
+            all_lower.select(index);
+        });
+        let total_lower_excess = all_lower.excess(target, Drain::NONE);
+        if total_lower_excess == 0 {
+            return self.finish_knapsack(target, change_policy, &applicable);
+        }
+        if total_lower_excess < 0 {
+            return match lowest_larger {
+                Some((index, _)) => self.finish_knapsack(target, change_policy, &[index]),
+                None => Err(SelectError::InsufficientFunds(InsufficientFunds {
+                    missing: total_lower_excess.unsigned_abs(),
+                })),
+            };
+        }
+
+        // Core sorts by descending effective value
+        applicable.sort_by_key(|&index| {
+            core::cmp::Reverse(Ordf32(
+                self.candidate(index).effective_value(target.fee.rate),
+            ))
+        });
+        let mut best = self.approximate_best_subset(target, &applicable, Drain::NONE, rng);
+        if best.excess(target, Drain::NONE) != 0 && all_lower.excess(target, with_change) >= 0 {
+            best = self.approximate_best_subset(target, &applicable, with_change, rng);
+        }
+
+        let best_excess = best.excess(target, Drain::NONE);
+        if let Some((index, larger_excess)) = lowest_larger {
+            let best_is_poor = best_excess != 0 && best.excess(target, with_change) < 0;
+            if best_is_poor || larger_excess <= best_excess {
+                return self.finish_knapsack(target, change_policy, &[index]);
+            }
+        }
+        let chosen = best.selected_indices().iter().collect::<Vec<_>>();
+        self.finish_knapsack(target, change_policy, &chosen)
+    }
+
+    /// Core's `ApproximateBestSubset`: randomly include candidates (then all the rest) until
+    /// `drain` can be funded, and keep the subset with the least excess.
+    fn approximate_best_subset<R: RandomSource>(
+        &self,
+        target: Target,
+        candidates: &[usize],
+        drain: Drain,
+        rng: &mut R,
+    ) -> Self {
+        let mut best = self.clone();
+        candidates.iter().for_each(|&index| {
+            best.select(index);
+        });
+        let mut best_excess = best.excess(target, drain);
+
+        for _ in 0..KNAPSACK_ITERATIONS {
+            if best_excess == 0 {
+                break;
+            }
+            let mut trial = self.clone();
+            let mut reached_target = false;
+            for pass in 0..2 {
+                if reached_target {
+                    break;
+                }
+                for &index in candidates {
+                    let include = if pass == 0 {
+                        rng.next_u64() & 1 == 1
+                    } else {
+                        !trial.is_selected(index)
+                    };
+                    if !include {
+                        continue;
+                    }
+                    trial.select(index);
+                    let excess = trial.excess(target, drain);
+                    if excess >= 0 {
+                        reached_target = true;
+                        if excess < best_excess {
+                            best_excess = excess;
+                            best = trial.clone();
+                        }
+                        trial.deselect(index);
+                    }
+                }
+            }
+        }
+        best
+    }
+
+    fn finish_knapsack(
+        &mut self,
+        target: Target,
+        change_policy: ChangePolicy,
+        indices: &[usize],
+    ) -> Result<Drain, SelectError> {
+        let mut selection = self.clone();
+        for &index in indices {
+            selection.select(index);
+        }
+        if !selection.is_truc_compatible(target) {
+            return Err(SelectError::TrucPolicyViolated);
+        }
+        let drain = selection.drain(target, change_policy);
+        if !selection.is_within_max_weight(target, drain.weights) {
+            return Err(SelectError::MaxWeightExceeded);
+        }
+        *self = selection;
+        Ok(drain)
+    }
+}
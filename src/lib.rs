@@ -19,6 +19,12 @@ pub use coin_selector::*;
 
 mod bnb;
 pub use bnb::*;
+mod budget;
+pub use budget::*;
+mod observer;
+pub use observer::*;
+mod session;
+pub use session::*;
 
 pub mod metrics;
 
@@ -28,6 +34,14 @@ mod target;
 pub use target::*;
 mod drain;
 pub use drain::*;
+mod random;
+pub use random::*;
+mod runner;
+pub use runner::*;
+mod knapsack;
+#[cfg(feature = "std")]
+mod parallel;
+mod random_draw;
 
 /// Txin "base" fields include `outpoint` (32+4) and `nSequence` (4) and 1 byte for the scriptSig
 /// length.
@@ -58,6 +72,13 @@ pub const TR_KEYSPEND_TXIN_WEIGHT: u64 = TXIN_BASE_WEIGHT + TR_KEYSPEND_SATISFAC
 /// fee
 pub const TR_DUST_RELAY_MIN_VALUE: u64 = 330;
 
+/// The most weight a TRUC (BIP-431, version 3) transaction can have (10,000 vbytes).
+pub const TRUC_MAX_WEIGHT: u64 = 10_000 * 4;
+
+/// The most weight a TRUC (BIP-431, version 3) transaction that spends an unconfirmed TRUC output
+/// can have (1,000 vbytes).
+pub const TRUC_CHILD_MAX_WEIGHT: u64 = 1_000 * 4;
+
 /// Helper to calculate varint size. `v` is the value the varint represents.
 const fn varint_size(v: usize) -> u64 {
     if v <= 0xfc {
@@ -3,7 +3,330 @@
 //!
 //! [`CoinSelector::bnb_solutions`]: crate::CoinSelector::bnb_solutions
 //! [`CoinSelector::run_bnb`]: crate::CoinSelector::run_bnb
+use crate::{float::Ordf32, Candidate, CoinSelector, Drain, DrainWeights, FeeRate, Target};
+use alloc::vec::Vec;
+
 mod lowest_fee;
 pub use lowest_fee::*;
 mod changeless;
 pub use changeless::*;
+mod waste;
+pub use waste::*;
+mod min_weight;
+pub use min_weight::*;
+mod exact_match;
+pub use exact_match::*;
+mod lexicographic;
+pub use lexicographic::*;
+mod weighted;
+pub use weighted::*;
+mod quantized;
+pub use quantized::*;
+mod consolidate;
+pub use consolidate::*;
+
+/// A lower bound on the input weight that must be added to `cs` to satisfy `target` with `drain`, or
+/// `None` if no descendant selection can satisfy it.
+///
+/// Each fee constraint is relaxed into a fractional knapsack: we pretend we can add a fraction of a
+/// candidate and greedily take the candidates with the most effective value per weight unit first.
+/// This is only a valid lower bound if the candidates are sorted by descending value per weight
+/// unit (see [`BnbMetric::requires_ordering_by_descending_value_pwu`]).
+///
+/// [`BnbMetric::requires_ordering_by_descending_value_pwu`]: crate::BnbMetric::requires_ordering_by_descending_value_pwu
+fn min_extra_input_weight(cs: &CoinSelector<'_>, target: Target, drain: Drain) -> Option<f32> {
+    // The `_wu` excesses ignore rounding up to vbytes, and the `- 1` accounts for rounding up the
+    // fee of the current selection, so neither overestimates what is missing.
+    let rate_missing = -cs.rate_excess_wu(target, drain) - 1;
+    let mut extra_weight =
+        min_weight_to_gain(cs, rate_missing, |c| c.effective_value(target.fee.rate))?;
+
+    // The replacement and absolute constraints don't care about bumping ancestors.
+    if let Some(replace) = target.fee.replace {
+        let replace_missing = -cs.replacement_excess_wu(target, drain) - 1;
+        let feerate = replace.incremental_relay_feerate;
+        extra_weight = extra_weight.max(min_weight_to_gain(cs, replace_missing, |c| {
+            c.value as f32 - c.weight as f32 * feerate.spwu()
+        })?);
+    }
+
+    let absolute_missing = -cs.absolute_excess(target, drain);
+    extra_weight = extra_weight.max(min_weight_to_gain(cs, absolute_missing, |c| {
+        c.value as f32
+    })?);
+
+    Some(extra_weight)
+}
+
+/// A lower bound on the input part of the [waste] of any changeless descendant of `cs` that
+/// satisfies `target`, or `None` if there is no such descendant. Like [`min_extra_input_weight`]
+/// this requires the candidates to be sorted by descending value per weight unit.
+///
+/// [waste]: crate::CoinSelector::waste
+fn input_waste_lower_bound(
+    cs: &CoinSelector<'_>,
+    target: Target,
+    long_term_feerate: FeeRate,
+) -> Option<f32> {
+    let rate_diff = target.fee.rate.spwu() - long_term_feerate.spwu();
+    // descendants only add to the bump fee, which is all waste
+    let selected_bump_fee = cs.bump_fee(target.fee.rate) as f32;
+    if rate_diff >= 0.0 {
+        // Every input adds waste, so the best descendant is the lightest one that is funded.
+        let extra_weight = min_extra_input_weight(cs, target, Drain::NONE)?;
+        if let Some(max_weight) = cs.weight_limit(target) {
+            if cs.weight(target.outputs, DrainWeights::NONE) as f32 + extra_weight
+                > max_weight as f32
+            {
+                return None;
+            }
+        }
+        Some((cs.input_weight() as f32 + extra_weight) * rate_diff + selected_bump_fee)
+    } else {
+        // Every input removes waste, so the best descendant is the heaviest one we can reach.
+        if !cs.is_fundable(target) {
+            return None;
+        }
+        let mut heaviest = cs.clone();
+        heaviest.select_all();
+        let mut max_input_weight = heaviest.input_weight();
+        if let Some(max_weight) = cs.weight_limit(target) {
+            let non_input_weight =
+                cs.weight(target.outputs, DrainWeights::NONE) - cs.input_weight();
+            max_input_weight = max_input_weight.min(max_weight.saturating_sub(non_input_weight));
+        }
+        Some(max_input_weight as f32 * rate_diff + selected_bump_fee)
+    }
+}
+
+/// Whether `cs` meets the constraints every solution must meet besides funding `target` and fitting
+/// [`Target::max_weight`] (which depend on the change output): it spends one of the
+/// [`CoinSelector::conflicts`], has an input count within [`Target::min_input_count`] and
+/// [`Target::max_input_count`] and follows the TRUC policy.
+pub(crate) fn meets_constraints(cs: &CoinSelector<'_>, target: Target) -> bool {
+    cs.is_conflicting() && cs.is_within_input_count(target) && cs.is_truc_compatible(target)
+}
+
+/// Whether `cs` or one of its descendants could fit [`Target::max_weight`] with a change output of
+/// `drain_weights` and meet the constraints of [`meets_constraints`].
+///
+/// Descendants only add inputs, so if `cs` is already too heavy, has every conflict banned, can't
+/// reach an allowed input count or spends unconfirmed candidates the TRUC policy doesn't allow, so
+/// does every descendant and the branch can be pruned.
+pub(crate) fn can_meet_constraints(
+    cs: &CoinSelector<'_>,
+    target: Target,
+    drain_weights: DrainWeights,
+) -> bool {
+    cs.is_within_max_weight(target, drain_weights)
+        && cs.can_conflict()
+        && can_reach_input_count(cs, target)
+        && cs.is_truc_compatible(target)
+}
+
+/// Whether a descendant of `cs` could satisfy `target` with an input count within
+/// [`Target::min_input_count`] and [`Target::max_input_count`].
+///
+/// Selecting more candidates only adds inputs, so `cs` must not have too many already and the
+/// unselected candidates must be able to make up for too few. For the maximum we also relax funding
+/// the feerate into a fractional knapsack over the input count of the candidates: if even the
+/// fewest (fractional) inputs that could fund it are too many, so is every descendant.
+pub(crate) fn can_reach_input_count(cs: &CoinSelector<'_>, target: Target) -> bool {
+    let input_count = cs.input_count();
+    if let Some(min_input_count) = target.min_input_count {
+        let available = cs.unselected().map(|(_, c)| c.input_count).sum::<usize>();
+        if input_count + available < min_input_count {
+            return false;
+        }
+    }
+    if let Some(max_input_count) = target.max_input_count {
+        if input_count > max_input_count {
+            return false;
+        }
+        let missing = -cs.rate_excess_wu(target, Drain::NONE) - 1;
+        let mut gains = cs
+            .unselected()
+            .map(|(_, c)| (c.effective_value(target.fee.rate), c.input_count as f32))
+            .filter(|&(gain, _)| gain > 0.0)
+            .collect::<Vec<_>>();
+        gains.sort_by_key(|&(gain, count)| core::cmp::Reverse(Ordf32(gain / count)));
+        match fractional_weight_to_gain(gains.into_iter(), missing) {
+            Some(extra_input_count)
+                if input_count as f32 + extra_input_count <= max_input_count as f32 => {}
+            _ => return false,
+        }
+    }
+    true
+}
+
+/// A lower bound on the `cost` of the unselected candidates that must be added to `cs` for their
+/// `gain` to sum to at least `min_gain` while their weight sums to at most `max_added_weight` (if
+/// any). Returns `None` if no candidates can do that.
+///
+/// This is the linear programming relaxation of the two-constraint knapsack, where we pretend we
+/// can add a fraction of a candidate. Without a weight limit it's the fractional knapsack: take the
+/// candidates with the most gain per cost first. With one, every unit of weight is given a price
+/// and the price that gives the highest (Lagrangian) bound is searched for, so heavy candidates are
+/// charged for using up the room left under the limit. Any price gives a lower bound, so it's
+/// always admissible even if the search doesn't find the best price exactly.
+///
+/// Candidates with no gain are never used, and the `cost` of a candidate must not be negative.
+///
+/// E.g. a metric that minimizes the fee can take the effective value of a candidate as the `gain`,
+/// the fee it adds as the `cost` and the room left under [`Target::max_weight`] as the
+/// `max_added_weight`.
+pub fn lp_min_cost(
+    cs: &CoinSelector<'_>,
+    min_gain: f32,
+    max_added_weight: Option<f32>,
+    gain: impl Fn(&Candidate) -> f32,
+    cost: impl Fn(&Candidate) -> f32,
+) -> Option<f32> {
+    if max_added_weight.map_or(false, |max_added_weight| max_added_weight < 0.0) {
+        return None;
+    }
+    if min_gain <= 0.0 {
+        return Some(0.0);
+    }
+    let mut items = cs
+        .unselected()
+        .map(|(_, c)| LpItem {
+            gain: gain(&c) as f64,
+            cost: cost(&c) as f64,
+            weight: c.weight as f64,
+        })
+        .filter(|item| item.gain > 0.0)
+        .collect::<Vec<_>>();
+
+    let (cost, weight) = cheapest_to_gain(&mut items, min_gain as f64, 0.0)?;
+    let max_added_weight = match max_added_weight {
+        Some(max_added_weight) if weight > max_added_weight as f64 => max_added_weight as f64,
+        _ => return Some(cost as f32),
+    };
+
+    // Even the lightest way to gain enough is too heavy.
+    let (_, min_weight) = cheapest_to_gain(&mut items, min_gain as f64, f64::INFINITY)?;
+    if min_weight > max_added_weight {
+        return None;
+    }
+
+    // The Lagrangian bound for a `price` of weight is concave in the price, and it's highest where
+    // the cheapest selection stops being too heavy, so we bisect on that.
+    let mut best = cost;
+    let mut lagrangian_bound = |items: &mut [LpItem], price: f64| {
+        let (cost, weight) = cheapest_to_gain(items, min_gain as f64, price)
+            .expect("we checked there is enough gain");
+        best = best.max(cost + price * (weight - max_added_weight));
+        weight > max_added_weight
+    };
+    let mut low = 0.0_f64;
+    let mut high = items
+        .iter()
+        .map(|item| item.cost / item.weight)
+        .fold(1.0, f64::max);
+    for _ in 0..64 {
+        if !lagrangian_bound(&mut items, high) {
+            break;
+        }
+        low = high;
+        high *= 2.0;
+    }
+    for _ in 0..32 {
+        let price = (low + high) / 2.0;
+        if lagrangian_bound(&mut items, price) {
+            low = price;
+        } else {
+            high = price;
+        }
+    }
+    Some(best as f32)
+}
+
+#[derive(Debug, Clone, Copy)]
+struct LpItem {
+    gain: f64,
+    cost: f64,
+    weight: f64,
+}
+
+/// The cost and weight of the (fractional) items with the least `cost + price * weight` whose gain
+/// sums to `min_gain`, or `None` if all of them together don't gain enough. An infinite `price`
+/// finds the lightest items.
+fn cheapest_to_gain(items: &mut [LpItem], min_gain: f64, price: f64) -> Option<(f64, f64)> {
+    let priced = |item: &LpItem| {
+        if price.is_infinite() {
+            item.weight / item.gain
+        } else {
+            (item.cost + price * item.weight) / item.gain
+        }
+    };
+    items.sort_by(|a, b| {
+        priced(a)
+            .partial_cmp(&priced(b))
+            .unwrap_or(core::cmp::Ordering::Equal)
+    });
+    let mut missing = min_gain;
+    let (mut cost, mut weight) = (0.0, 0.0);
+    for item in items.iter() {
+        let fraction = (missing / item.gain).min(1.0);
+        cost += item.cost * fraction;
+        weight += item.weight * fraction;
+        missing -= item.gain * fraction;
+        if fraction < 1.0 {
+            return Some((cost, weight));
+        }
+    }
+    if missing > 0.0 {
+        return None;
+    }
+    Some((cost, weight))
+}
+
+/// The least (fractional) weight of unselected candidates whose `gain` sums to `missing`.
+///
+/// Assumes candidates are sorted by descending value per weight unit. That is only the same as
+/// sorting them by `gain` per weight unit if none of them have ancestors to bump, so otherwise we
+/// sort them here.
+fn min_weight_to_gain(
+    cs: &CoinSelector<'_>,
+    missing: i64,
+    gain: impl Fn(&Candidate) -> f32,
+) -> Option<f32> {
+    let gains = cs
+        .unselected()
+        .map(|(_, candidate)| (gain(&candidate), candidate.weight as f32));
+    if cs.unselected().any(|(_, c)| c.ancestors.is_some()) {
+        let mut gains = gains.filter(|&(gain, _)| gain > 0.0).collect::<Vec<_>>();
+        gains.sort_by_key(|&(gain, weight)| core::cmp::Reverse(Ordf32(gain / weight)));
+        fractional_weight_to_gain(gains.into_iter(), missing)
+    } else {
+        fractional_weight_to_gain(gains, missing)
+    }
+}
+
+/// Takes `(gain, weight)` pairs in order until their gains sum to `missing`, taking a fraction of
+/// the last one.
+fn fractional_weight_to_gain(gains: impl Iterator<Item = (f32, f32)>, missing: i64) -> Option<f32> {
+    let mut missing = missing as f32;
+    let mut weight = 0.0_f32;
+    for (gain, gain_weight) in gains {
+        if missing <= 0.0 {
+            break;
+        }
+        if gain <= 0.0 {
+            // sorted by gain per weight unit, so the rest can't help either
+            break;
+        }
+        if gain >= missing {
+            weight += gain_weight * (missing / gain);
+            missing = 0.0;
+        } else {
+            weight += gain_weight;
+            missing -= gain;
+        }
+    }
+    if missing > 0.0 {
+        return None;
+    }
+    Some(weight)
+}
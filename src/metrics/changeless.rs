@@ -1,4 +1,4 @@
-use crate::{bnb::BnbMetric, float::Ordf32, CoinSelector, Drain, Target};
+use crate::{bnb::BnbMetric, Candidate, CoinSelector, Drain, Target};
 
 /// Constrains an `inner` metric to only changeless solutions.
 ///
@@ -22,34 +22,47 @@ impl<M: BnbMetric> Changeless<M> {
     /// negative-effective-value candidate, since each of those lowers the excess. If even that
     /// selection still has change, then so does every reachable selection.
     ///
-    /// NOTE: this relies on candidates being sorted so that all negative effective value candidates
-    /// are next to each other, which [`requires_ordering_by_descending_value_pwu`] guarantees.
+    /// NOTE: [`requires_ordering_by_descending_value_pwu`] puts all negative effective value
+    /// candidates next to each other at the end, unless candidates have [`ancestors`] to bump, in
+    /// which case we have to look at all of them.
     ///
     /// [`requires_ordering_by_descending_value_pwu`]: BnbMetric::requires_ordering_by_descending_value_pwu
+    /// [`ancestors`]: crate::Candidate::ancestors
     fn change_unavoidable(&mut self, cs: &CoinSelector<'_>, target: Target) -> bool {
         if self.0.drain(cs, target).is_none() {
             return false;
         }
 
         let mut least_excess = cs.clone();
-        cs.unselected()
-            .rev()
-            .take_while(|(_, wv)| wv.effective_value(target.fee.rate) < 0.0)
-            .for_each(|(index, _)| {
-                least_excess.select(index);
-            });
+        let is_negative = |wv: &Candidate| wv.effective_value(target.fee.rate) < 0.0;
+        if cs.unselected().any(|(_, wv)| wv.ancestors.is_some()) {
+            cs.unselected()
+                .filter(|(_, wv)| is_negative(wv))
+                .for_each(|(index, _)| {
+                    least_excess.select(index);
+                });
+        } else {
+            cs.unselected()
+                .rev()
+                .take_while(|(_, wv)| is_negative(wv))
+                .for_each(|(index, _)| {
+                    least_excess.select(index);
+                });
+        }
 
         self.0.drain(&least_excess, target).is_some()
     }
 }
 
 impl<M: BnbMetric> BnbMetric for Changeless<M> {
+    type Score = M::Score;
+
     fn drain(&mut self, _cs: &CoinSelector<'_>, _target: Target) -> Drain {
         // by definition a changeless selection never has a change output
         Drain::NONE
     }
 
-    fn score(&mut self, cs: &CoinSelector<'_>, target: Target) -> Option<Ordf32> {
+    fn score(&mut self, cs: &CoinSelector<'_>, target: Target) -> Option<Self::Score> {
         // Reject selections that have change. We don't need an explicit target-met check: `inner`
         // returns `None` for invalid (e.g. not-target-met) selections.
         //
@@ -62,7 +75,7 @@ impl<M: BnbMetric> BnbMetric for Changeless<M> {
         self.0.score(cs, target)
     }
 
-    fn bound(&mut self, cs: &CoinSelector<'_>, target: Target) -> Option<Ordf32> {
+    fn bound(&mut self, cs: &CoinSelector<'_>, target: Target) -> Option<Self::Score> {
         if self.change_unavoidable(cs, target) {
             // every descendant has change, so no changeless solution is reachable
             None
@@ -0,0 +1,168 @@
+use super::{can_meet_constraints, meets_constraints, min_extra_input_weight};
+use crate::{float::Ordf32, BnbMetric, CoinSelector, Drain, DrainWeights, FeeRate, Target};
+
+/// Metric that aims to spend as many inputs as possible while fees are low.
+///
+/// Each input we spend now instead of later saves us the cost of spending it at the
+/// `long_term_feerate` but costs us its fee at `target.fee.rate`. `Consolidate` maximizes these
+/// savings, i.e. it minimizes:
+///
+/// > `input_weight * target.fee.rate - sum(candidate.weight) * long_term_feerate`
+///
+/// where `input_weight` also includes the varint and witness header of the inputs, which are only
+/// paid for now. The same goes for the [`bump_fee`] of any unconfirmed ancestors, which is added to
+/// the score.
+///
+/// When `target.fee.rate` is below `long_term_feerate` every input adds to the savings, so this
+/// spends as many inputs as it can within [`Target::max_weight`] and the `fee_budget`. When it is
+/// above, every input is a loss and it spends as few as it can.
+///
+/// Change is added like [`LowestFee`] does: whenever the recovered excess outweighs the future cost
+/// of spending the change and the change would not be dust.
+///
+/// [`LowestFee`]: crate::metrics::LowestFee
+/// [`bump_fee`]: crate::CoinSelector::bump_fee
+#[derive(Clone, Copy, Debug)]
+pub struct Consolidate {
+    /// The estimated feerate needed to spend our inputs and change output later.
+    pub long_term_feerate: FeeRate,
+    /// The feerate used to determine the dust threshold of the change output.
+    pub dust_relay_feerate: FeeRate,
+    /// The weights of the change output that would be added.
+    pub drain_weights: DrainWeights,
+    /// The most fee the transaction may pay, if any.
+    pub fee_budget: Option<u64>,
+}
+
+impl Consolidate {
+    /// The value the change output should have, or `None` if this selection should be changeless.
+    fn drain_value(&self, cs: &CoinSelector<'_>, target: Target) -> Option<u64> {
+        let excess_with_drain_weight = cs.excess(
+            target,
+            Drain {
+                weights: self.drain_weights,
+                value: 0,
+            },
+        );
+        let drain_spend_cost = self
+            .long_term_feerate
+            .implied_fee_wu(self.drain_weights.spend_weight);
+        if excess_with_drain_weight <= drain_spend_cost as i64 {
+            return None;
+        }
+        let dust_threshold = self.drain_weights.dust_threshold(self.dust_relay_feerate);
+        if excess_with_drain_weight < dust_threshold as i64 {
+            return None;
+        }
+        if !cs.is_within_max_weight(target, self.drain_weights) {
+            return None;
+        }
+        Some(excess_with_drain_weight.unsigned_abs())
+    }
+
+    /// The score of a selection with `input_weight`, `candidate_weight` (the input weight without
+    /// the varint and witness header) and `bump_fee`, without any of the validity checks.
+    fn savings_score(
+        &self,
+        target: Target,
+        input_weight: f32,
+        candidate_weight: f32,
+        bump_fee: u64,
+    ) -> f32 {
+        input_weight * target.fee.rate.spwu() - candidate_weight * self.long_term_feerate.spwu()
+            + bump_fee as f32
+    }
+
+    fn candidate_weight(cs: &CoinSelector<'_>) -> u64 {
+        cs.selected().map(|(_, c)| c.weight).sum()
+    }
+}
+
+impl BnbMetric for Consolidate {
+    type Score = Ordf32;
+
+    fn drain(&mut self, cs: &CoinSelector<'_>, target: Target) -> Drain {
+        self.drain_value(cs, target)
+            .map_or(Drain::NONE, |value| Drain {
+                weights: self.drain_weights,
+                value,
+            })
+    }
+
+    fn score(&mut self, cs: &CoinSelector<'_>, target: Target) -> Option<Ordf32> {
+        if !cs.is_funded(target) || !meets_constraints(cs, target) {
+            return None;
+        }
+        let drain = self.drain(cs, target);
+        if !cs.is_within_max_weight(target, drain.weights) {
+            return None;
+        }
+        if let Some(fee_budget) = self.fee_budget {
+            let fee = cs.fee(target.value(), drain.value);
+            if fee > fee_budget as i64 {
+                return None;
+            }
+        }
+        Some(Ordf32(self.savings_score(
+            target,
+            cs.input_weight() as f32,
+            Self::candidate_weight(cs) as f32,
+            cs.bump_fee(target.fee.rate),
+        )))
+    }
+
+    fn bound(&mut self, cs: &CoinSelector<'_>, target: Target) -> Option<Ordf32> {
+        if !can_meet_constraints(cs, target, DrainWeights::NONE) {
+            return None;
+        }
+
+        // Whether or not there is change, a solution pays at least the fee implied by its weight
+        // without change, and that only grows down this branch.
+        let weight = cs.weight(target.outputs, DrainWeights::NONE);
+        if let Some(fee_budget) = self.fee_budget {
+            if target.fee.rate.implied_fee(weight) > fee_budget {
+                return None;
+            }
+        }
+
+        // The input overhead only grows, so each added candidate changes the score by at least
+        // `weight * (feerate - long_term_feerate)`. The best descendant is the one that adds as
+        // little or as much weight as it can, depending on the sign.
+        let rate_diff = target.fee.rate.spwu() - self.long_term_feerate.spwu();
+        let added_weight = if rate_diff >= 0.0 {
+            let extra_weight = min_extra_input_weight(cs, target, Drain::NONE)?;
+            if let Some(max_weight) = cs.weight_limit(target) {
+                if weight as f32 + extra_weight > max_weight as f32 {
+                    return None;
+                }
+            }
+            extra_weight
+        } else {
+            if !cs.is_fundable(target) {
+                return None;
+            }
+            let mut room = cs.unselected().map(|(_, c)| c.weight).sum::<u64>() as f32;
+            if let Some(max_weight) = cs.weight_limit(target) {
+                room = room.min(max_weight.saturating_sub(weight) as f32);
+            }
+            if let Some(fee_budget) = self.fee_budget {
+                if target.fee.rate.spwu() > 0.0 {
+                    room = room.min(fee_budget as f32 / target.fee.rate.spwu() - weight as f32);
+                }
+            }
+            room.max(0.0)
+        };
+
+        Some(Ordf32(self.savings_score(
+            target,
+            cs.input_weight() as f32 + added_weight,
+            Self::candidate_weight(cs) as f32 + added_weight,
+            // descendants can only add to it
+            cs.bump_fee(target.fee.rate),
+        )))
+    }
+
+    fn requires_ordering_by_descending_value_pwu(&self) -> bool {
+        true
+    }
+}
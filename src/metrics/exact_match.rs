@@ -0,0 +1,106 @@
+use super::{can_meet_constraints, input_waste_lower_bound, meets_constraints};
+use crate::{float::Ordf32, BnbMetric, CoinSelector, Drain, DrainWeights, FeeRate, Target};
+
+/// Metric that only accepts changeless selections whose excess is within `[0, cost_of_change]` and
+/// minimizes their waste, like Bitcoin Core's branch and bound.
+///
+/// Unlike [`Changeless`], which leaves the change decision to an inner metric, the window here is
+/// explicit. A good `cost_of_change` is the `min_value` of a [`ChangePolicy`] made with
+/// [`ChangePolicy::min_value_and_waste`]: if the excess is above it, adding change would be cheaper.
+///
+/// Since the window is explicit, a branch is pruned as soon as even its smallest possible excess
+/// is above `cost_of_change`. This prunes much more than [`Changeless`] can.
+///
+/// [`Changeless`]: crate::metrics::Changeless
+/// [`ChangePolicy`]: crate::ChangePolicy
+/// [`ChangePolicy::min_value_and_waste`]: crate::ChangePolicy::min_value_and_waste
+#[derive(Clone, Copy, Debug)]
+pub struct ExactMatch {
+    /// The estimated feerate needed to spend our inputs later.
+    pub long_term_feerate: FeeRate,
+    /// The largest excess a selection can have. Anything above this is better spent on change.
+    pub cost_of_change: u64,
+}
+
+impl ExactMatch {
+    /// A lower bound on the excess of the current selection and all of its descendants.
+    ///
+    /// Each remaining candidate can lower the excess by at most its negative effective value, and
+    /// the transaction overhead of the inputs (varint and witness header) can grow by at most what
+    /// it would be with every candidate selected. We also allow for the vbyte rounding of the
+    /// selection's fee.
+    fn min_descendant_excess(&self, cs: &CoinSelector<'_>, target: Target) -> f32 {
+        fn input_overhead(cs: &CoinSelector<'_>) -> u64 {
+            cs.input_weight() - cs.selected().map(|(_, c)| c.weight).sum::<u64>()
+        }
+        let mut all = cs.clone();
+        all.select_all();
+        let extra_weight = (input_overhead(&all) - input_overhead(cs)) as f32;
+
+        let min_excess_at = |excess: i64, feerate: FeeRate| -> f32 {
+            let lost_value = cs
+                .unselected()
+                .map(|(_, c)| c.effective_value(feerate).min(0.0))
+                .sum::<f32>();
+            excess as f32 + lost_value - feerate.spwu() * (extra_weight + 3.0) - 1.0
+        };
+
+        let mut min_excess = min_excess_at(cs.rate_excess(target, Drain::NONE), target.fee.rate)
+            // adding inputs never lowers the absolute excess
+            .min(cs.absolute_excess(target, Drain::NONE) as f32);
+        if let Some(replace) = target.fee.replace {
+            min_excess = min_excess.min(min_excess_at(
+                cs.replacement_excess(target, Drain::NONE),
+                replace.incremental_relay_feerate,
+            ));
+        }
+        min_excess
+    }
+}
+
+impl BnbMetric for ExactMatch {
+    type Score = Ordf32;
+
+    fn drain(&mut self, _cs: &CoinSelector<'_>, _target: Target) -> Drain {
+        Drain::NONE
+    }
+
+    fn score(&mut self, cs: &CoinSelector<'_>, target: Target) -> Option<Ordf32> {
+        let excess = cs.excess(target, Drain::NONE);
+        if excess < 0 || excess as u64 > self.cost_of_change {
+            return None;
+        }
+        if !meets_constraints(cs, target) {
+            return None;
+        }
+        if !cs.is_within_max_weight(target, DrainWeights::NONE) {
+            return None;
+        }
+        Some(Ordf32(cs.waste(
+            target,
+            self.long_term_feerate,
+            Drain::NONE,
+            1.0,
+        )))
+    }
+
+    fn bound(&mut self, cs: &CoinSelector<'_>, target: Target) -> Option<Ordf32> {
+        if !can_meet_constraints(cs, target, DrainWeights::NONE) {
+            return None;
+        }
+
+        let min_excess = self.min_descendant_excess(cs, target);
+        if min_excess > self.cost_of_change as f32 {
+            return None;
+        }
+
+        // The waste of a solution is its input waste plus its excess, so we can bound each part on
+        // its own.
+        let input_waste = input_waste_lower_bound(cs, target, self.long_term_feerate)?;
+        Some(Ordf32(input_waste + min_excess.max(0.0)))
+    }
+
+    fn requires_ordering_by_descending_value_pwu(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,54 @@
+use crate::{BnbMetric, CoinSelector, Drain, Target};
+
+/// Combines two metrics so that the `primary` metric is minimized first and the `secondary` metric
+/// breaks ties between selections with the same `primary` score.
+///
+/// The score is the pair of the two scores, compared in order, so no precision is lost however far
+/// apart the scores are. To let the `secondary` metric break ties between `primary` scores that are
+/// merely close, wrap the `primary` metric in [`Quantized`]. For example, with
+/// `Quantized { metric: LowestFee { .. }, tolerance: 1_000.0 }` as the `primary` metric and a metric
+/// that counts inputs as the `secondary` metric, this picks the selection with the fewest inputs
+/// among the ones whose fee rounds down to the same multiple of 1,000 sats as the lowest fee.
+///
+/// A selection is only valid if both metrics give it a score. The change output is decided by the
+/// `primary` metric alone, so the `secondary` metric should not score selections differently
+/// depending on whether they have change.
+///
+/// [`Quantized`]: crate::metrics::Quantized
+#[derive(Clone, Copy, Debug)]
+pub struct Lexicographic<A, B> {
+    /// The metric that is minimized first. It also decides the change output.
+    pub primary: A,
+    /// The metric that breaks ties between selections with the same `primary` score.
+    pub secondary: B,
+}
+
+impl<A, B> BnbMetric for Lexicographic<A, B>
+where
+    A: BnbMetric,
+    B: BnbMetric,
+{
+    type Score = (A::Score, B::Score);
+
+    fn drain(&mut self, cs: &CoinSelector<'_>, target: Target) -> Drain {
+        self.primary.drain(cs, target)
+    }
+
+    fn score(&mut self, cs: &CoinSelector<'_>, target: Target) -> Option<Self::Score> {
+        let primary = self.primary.score(cs, target)?;
+        let secondary = self.secondary.score(cs, target)?;
+        Some((primary, secondary))
+    }
+
+    fn bound(&mut self, cs: &CoinSelector<'_>, target: Target) -> Option<Self::Score> {
+        // no descendant scores below either inner bound, so none compares below the pair of them
+        let primary = self.primary.bound(cs, target)?;
+        let secondary = self.secondary.bound(cs, target)?;
+        Some((primary, secondary))
+    }
+
+    fn requires_ordering_by_descending_value_pwu(&self) -> bool {
+        self.primary.requires_ordering_by_descending_value_pwu()
+            || self.secondary.requires_ordering_by_descending_value_pwu()
+    }
+}
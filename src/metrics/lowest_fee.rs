@@ -1,4 +1,6 @@
+use super::{can_meet_constraints, lp_min_cost, meets_constraints, min_extra_input_weight};
 use crate::{float::Ordf32, BnbMetric, CoinSelector, Drain, DrainWeights, FeeRate, Target};
+use alloc::vec::Vec;
 
 /// Metric that aims to minimize transaction fees. The future fee for spending the change output is
 /// included in this calculation.
@@ -15,25 +17,54 @@ use crate::{float::Ordf32, BnbMetric, CoinSelector, Drain, DrainWeights, FeeRate
 /// output: change is added whenever doing so lowers the long-term fee (i.e. the recovered excess
 /// outweighs the future cost of spending the change) and the resulting change value is above the
 /// dust threshold implied by `dust_relay_feerate`.
-#[derive(Clone, Copy)]
+///
+/// If you can create more than one type of change output (e.g. P2WPKH or P2TR), pass all of them
+/// in `drain_options` and the one with the lowest long-term fee is picked for each selection.
+#[derive(Clone, Debug)]
 pub struct LowestFee {
     /// The estimated feerate needed to spend our change output later.
     pub long_term_feerate: FeeRate,
     /// The feerate used to determine the dust threshold of the change output.
     pub dust_relay_feerate: FeeRate,
-    /// The weights of the change output that would be added.
-    pub drain_weights: DrainWeights,
+    /// The weights of the change outputs that could be added. If two options are as good, the one
+    /// that comes first is picked.
+    pub drain_options: Vec<DrainWeights>,
 }
 
 impl LowestFee {
-    /// The value the change output should have, or `None` if this selection should be changeless.
-    fn drain_value(&self, cs: &CoinSelector<'_>, target: Target) -> Option<u64> {
+    /// The change output with the lowest long-term fee, or `None` if this selection should be
+    /// changeless.
+    fn best_drain(&self, cs: &CoinSelector<'_>, target: Target) -> Option<Drain> {
+        let mut best = Option::<(Drain, i64)>::None;
+        for &weights in &self.drain_options {
+            let value = match self.drain_value(cs, target, weights) {
+                Some(value) => value,
+                None => continue,
+            };
+            // The fee of the transaction is the same except for the change, so we only compare
+            // what the change gives back and what it costs to spend.
+            let long_term_fee = weights.spend_fee(self.long_term_feerate) as i64 - value as i64;
+            if best.map_or(true, |(_, best_fee)| long_term_fee < best_fee) {
+                best = Some((Drain { weights, value }, long_term_fee));
+            }
+        }
+        best.map(|(drain, _)| drain)
+    }
+
+    /// The value the change output with `drain_weights` should have, or `None` if it shouldn't be
+    /// added to this selection.
+    fn drain_value(
+        &self,
+        cs: &CoinSelector<'_>,
+        target: Target,
+        drain_weights: DrainWeights,
+    ) -> Option<u64> {
         // The change output pays for its own weight, so the value we'd actually recover is the
         // excess remaining after accounting for that weight.
         let excess_with_drain_weight = cs.excess(
             target,
             Drain {
-                weights: self.drain_weights,
+                weights: drain_weights,
                 value: 0,
             },
         );
@@ -42,13 +73,13 @@ impl LowestFee {
         // spending it (i.e. it lowers the long-term fee).
         let drain_spend_cost = self
             .long_term_feerate
-            .implied_fee_wu(self.drain_weights.spend_weight);
+            .implied_fee_wu(drain_weights.spend_weight);
         if excess_with_drain_weight <= drain_spend_cost as i64 {
             return None;
         }
 
         // ...and only if the change output would not be dust.
-        let dust_threshold = self.drain_weights.dust_threshold(self.dust_relay_feerate);
+        let dust_threshold = drain_weights.dust_threshold(self.dust_relay_feerate);
         if excess_with_drain_weight < dust_threshold as i64 {
             return None;
         }
@@ -56,7 +87,7 @@ impl LowestFee {
         // ...and only if the change output would not push the tx over `max_weight`. If it would,
         // we refuse the drain and the excess goes to fee instead (a slightly conservative choice:
         // it can refuse change even when a no-change tx of this selection would fit).
-        if !cs.is_within_max_weight(target, self.drain_weights) {
+        if !cs.is_within_max_weight(target, drain_weights) {
             return None;
         }
 
@@ -75,12 +106,7 @@ impl LowestFee {
         if !cs.is_funded(target) {
             return None;
         }
-        let drain = self
-            .drain_value(cs, target)
-            .map_or(Drain::NONE, |value| Drain {
-                weights: self.drain_weights,
-                value,
-            });
+        let drain = self.best_drain(cs, target).unwrap_or(Drain::NONE);
         let fee_for_the_tx = cs.fee(target.value(), drain.value);
         assert!(
             fee_for_the_tx >= 0,
@@ -96,15 +122,16 @@ impl LowestFee {
 }
 
 impl BnbMetric for LowestFee {
+    type Score = Ordf32;
+
     fn drain(&mut self, cs: &CoinSelector<'_>, target: Target) -> Drain {
-        self.drain_value(cs, target)
-            .map_or(Drain::NONE, |value| Drain {
-                weights: self.drain_weights,
-                value,
-            })
+        self.best_drain(cs, target).unwrap_or(Drain::NONE)
     }
 
     fn score(&mut self, cs: &CoinSelector<'_>, target: Target) -> Option<Ordf32> {
+        if !meets_constraints(cs, target) {
+            return None;
+        }
         let (score, drain) = self.fee_score(cs, target)?;
         // A final selection must fit the weight cap. `drain_value` already refuses an over-cap
         // change, but a changeless selection can still be too heavy on its own. Reuse the drain
@@ -118,18 +145,19 @@ impl BnbMetric for LowestFee {
     fn bound(&mut self, cs: &CoinSelector<'_>, target: Target) -> Option<Ordf32> {
         // Weight hard-prune: input weight only grows as this branch is extended, so the lightest
         // solution in the subtree is this selection with no drain. If even that busts `max_weight`,
-        // the whole subtree is infeasible -> prune. (Also keeps `fee_score(cs).unwrap()` below
-        // sound: a value-met but over-cap node would otherwise score `None`.)
-        if !cs.is_within_max_weight(target, DrainWeights::NONE) {
+        // the whole subtree is infeasible -> prune. The same goes for the conflict, input count and
+        // TRUC constraints. (Also keeps `fee_score(cs).unwrap()` below sound: a value-met but
+        // over-cap node would otherwise score `None`.)
+        if !can_meet_constraints(cs, target, DrainWeights::NONE) {
             return None;
         }
 
         if cs.is_funded(target) {
             let current_score = self.fee_score(cs, target).unwrap().0;
 
-            // `current_score` is already a valid lower bound for a selection that has change: a
-            // descendant can never lower the fee by removing an existing (worthwhile) change
-            // output.
+            // `current_score` is already a valid lower bound for the selections that have a change
+            // option that is already worthwhile here: a descendant can never lower the fee by
+            // removing an existing (worthwhile) change output.
             //
             // Proof: let A be a selection with worthwhile change and let B = A + one extra input of
             // value `v >= 0` that makes B changeless. The long-term fee (LTF, i.e. the score) of
@@ -145,39 +173,74 @@ impl BnbMetric for LowestFee {
             // Change is only added when it's worthwhile, i.e. `change_value > spend_fee` (see
             // `drain_value`, where `change_value` is `excess_with_drain_weight` and `spend_fee` is
             // `drain_spend_cost`). With `v >= 0` the difference is strictly positive: B always
-            // costs more.
-            if self.drain_value(cs, target).is_none() {
-                // But a descendant might *add* a change output that improves the metric. This
-                // happens when the current selection is changeless only because the change would be
-                // dust: a descendant with more excess could clear the dust threshold and recover
-                // value that is currently burned to fees.
-                let cost_of_adding_change = self.drain_weights.waste(
+            // costs more. Likewise, keeping the same change option only gets more expensive with
+            // more inputs, and `current_score` is for the best option that is worthwhile here.
+            let mut bound = current_score;
+
+            // But a descendant might *add* a change output that improves the metric. This happens
+            // when a change option isn't worthwhile here only because it would be dust: a
+            // descendant with more excess could clear the dust threshold and recover value that is
+            // currently burned to fees.
+            let fee_without_excess = cs.fee(target.value(), 0) - cs.excess(target, Drain::NONE);
+            for &weights in &self.drain_options {
+                if self.drain_value(cs, target, weights).is_some() {
+                    continue;
+                }
+                let cost_of_adding_change = weights.waste(
                     target.fee.rate,
                     self.long_term_feerate,
                     target.outputs.n_outputs,
                 );
-                let cost_of_no_change = cs.excess(target, Drain::NONE);
-
                 let best_score_with_change =
-                    Ordf32(current_score.0 - cost_of_no_change as f32 + cost_of_adding_change);
+                    Ordf32(fee_without_excess as f32 + cost_of_adding_change);
                 // max_weight-aware: realizing that improvement requires a change output AND at
                 // least one more input to lift the excess over the dust/worthwhile threshold, both
                 // of which only make the tx heavier. If there's no room for both under the cap the
-                // improvement is unreachable down this branch, so don't credit it — keep
-                // `current_score` (a tighter, still-admissible bound).
-                let change_is_reachable = match target.max_weight {
+                // improvement is unreachable down this branch, so don't credit it.
+                let change_is_reachable = match cs.weight_limit(target) {
                     None => true,
                     Some(max_weight) => cs.min_input_weight().map_or(false, |min_input_weight| {
-                        cs.weight(target.outputs, self.drain_weights) + min_input_weight
-                            <= max_weight
+                        cs.weight(target.outputs, weights) + min_input_weight <= max_weight
                     }),
                 };
-                if change_is_reachable && best_score_with_change < current_score {
-                    return Some(best_score_with_change);
+                if change_is_reachable && best_score_with_change < bound {
+                    bound = best_score_with_change;
+                }
+            }
+
+            Some(bound)
+        } else if cs.unselected().any(|(_, c)| c.ancestors.is_some()) {
+            // Resizing the input that hits the target (below) relies on the candidates being
+            // sorted by effective value per weight unit, which bumping ancestors breaks. Fall back
+            // to the fee implied by the lightest weight that could reach the target, plus the bump
+            // fee we already have to pay.
+            let extra_weight = min_extra_input_weight(cs, target, Drain::NONE)?;
+            let min_weight = cs.weight(target.outputs, DrainWeights::NONE) as f32 + extra_weight;
+            if let Some(max_weight) = cs.weight_limit(target) {
+                if min_weight > max_weight as f32 {
+                    return None;
                 }
             }
+            let min_fee = min_weight * target.fee.rate.spwu() + cs.bump_fee(target.fee.rate) as f32;
+
+            // The inputs we add also pay to bump their own ancestors, so the lightest inputs aren't
+            // always the cheapest, and with `max_weight` the cheapest ones may not fit. Relax both
+            // the value and the weight constraints together to bound the fee the inputs add.
+            let rate_missing = -cs.rate_excess_wu(target, Drain::NONE) - 1;
+            let current_weight = cs.weight(target.outputs, DrainWeights::NONE) as f32;
+            let added_fee = lp_min_cost(
+                cs,
+                rate_missing as f32,
+                cs.weight_limit(target)
+                    .map(|max_weight| max_weight as f32 - current_weight),
+                |c| c.effective_value(target.fee.rate),
+                |c| c.weight as f32 * target.fee.rate.spwu() + c.bump_fee(target.fee.rate) as f32,
+            )?;
+            let lp_fee = current_weight * target.fee.rate.spwu()
+                + cs.bump_fee(target.fee.rate) as f32
+                + added_fee;
 
-            Some(current_score)
+            Some(Ordf32(min_fee.max(lp_fee).max(target.fee.absolute as f32)))
         } else {
             // Step 1: select everything up until the input that hits the target.
             let (mut cs, resize_index, to_resize) = cs
@@ -259,8 +322,10 @@ impl BnbMetric for LowestFee {
             // so if the current weight plus even that (fractional) minimum already busts the cap,
             // no within-cap selection down this branch reaches the target -> prune. This is the
             // fractional relaxation, so it never prunes a branch with an (integer) within-cap
-            // solution.
-            if let Some(max_weight) = target.max_weight {
+            // solution. Without ancestors the fee an input adds is proportional to its weight, so
+            // the lightest way to reach the feerate is also the cheapest and relaxing the value and
+            // weight constraints together (`lp_min_cost`) can't give a higher bound than this.
+            if let Some(max_weight) = cs.weight_limit(target) {
                 if cs.weight(target.outputs, DrainWeights::NONE) as f32
                     + scale.0 * to_resize.weight as f32
                     > max_weight as f32
@@ -0,0 +1,86 @@
+use super::{can_meet_constraints, meets_constraints, min_extra_input_weight};
+use crate::{float::Ordf32, BnbMetric, CoinSelector, Drain, DrainWeights, FeeRate, Target};
+
+/// Metric that aims to minimize the weight of the transaction, like Bitcoin Core's CoinGrinder.
+///
+/// This is useful when feerates are high: the lightest transaction is the cheapest one to get
+/// confirmed right now. Unlike [`LowestFee`], `MinWeight` always adds a change output, so a
+/// selection is only valid if it can fund the target *and* a change output that is not dust. The
+/// drain it returns is only [`Drain::NONE`] for selections that are not valid.
+///
+/// [`LowestFee`]: crate::metrics::LowestFee
+#[derive(Clone, Copy, Debug)]
+pub struct MinWeight {
+    /// The feerate used to determine the dust threshold of the change output.
+    pub dust_relay_feerate: FeeRate,
+    /// The weights of the change output that is always added.
+    pub drain_weights: DrainWeights,
+}
+
+impl MinWeight {
+    /// The value of the change output, or `None` if the selection can't fund a change output that
+    /// is not dust (or that fits within `max_weight`).
+    fn drain_value(&self, cs: &CoinSelector<'_>, target: Target) -> Option<u64> {
+        let excess_with_drain_weight = cs.excess(
+            target,
+            Drain {
+                weights: self.drain_weights,
+                value: 0,
+            },
+        );
+        let dust_threshold = self.drain_weights.dust_threshold(self.dust_relay_feerate);
+        if excess_with_drain_weight < dust_threshold as i64 {
+            return None;
+        }
+        if !cs.is_within_max_weight(target, self.drain_weights) {
+            return None;
+        }
+        Some(excess_with_drain_weight.unsigned_abs())
+    }
+}
+
+impl BnbMetric for MinWeight {
+    type Score = Ordf32;
+
+    fn drain(&mut self, cs: &CoinSelector<'_>, target: Target) -> Drain {
+        self.drain_value(cs, target)
+            .map_or(Drain::NONE, |value| Drain {
+                weights: self.drain_weights,
+                value,
+            })
+    }
+
+    fn score(&mut self, cs: &CoinSelector<'_>, target: Target) -> Option<Ordf32> {
+        if !meets_constraints(cs, target) {
+            return None;
+        }
+        self.drain_value(cs, target)?;
+        Some(Ordf32(cs.weight(target.outputs, self.drain_weights) as f32))
+    }
+
+    fn bound(&mut self, cs: &CoinSelector<'_>, target: Target) -> Option<Ordf32> {
+        // Every solution has the change output.
+        if !can_meet_constraints(cs, target, self.drain_weights) {
+            return None;
+        }
+
+        // The lightest descendant adds the least weight that funds the target plus a change output
+        // at the dust threshold.
+        let min_drain = Drain {
+            weights: self.drain_weights,
+            value: self.drain_weights.dust_threshold(self.dust_relay_feerate),
+        };
+        let extra_weight = min_extra_input_weight(cs, target, min_drain)?;
+        let lightest_weight = cs.weight(target.outputs, self.drain_weights) as f32 + extra_weight;
+        if let Some(max_weight) = cs.weight_limit(target) {
+            if lightest_weight > max_weight as f32 {
+                return None;
+            }
+        }
+        Some(Ordf32(lightest_weight))
+    }
+
+    fn requires_ordering_by_descending_value_pwu(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,56 @@
+use crate::{
+    float::{FloatExt, Ordf32},
+    BnbMetric, CoinSelector, Drain, Target,
+};
+
+/// Rounds the scores of a `metric` down to a multiple of `tolerance`.
+///
+/// Selections whose scores round to the same multiple compare equal, which is mostly useful as the
+/// `primary` metric of [`Lexicographic`] so that its `secondary` metric can break ties between
+/// selections that score about the same. Note that scores are compared in buckets of `tolerance`
+/// rather than pairwise: two scores less than `tolerance` apart can still round to different
+/// multiples, e.g. `0.99` and `1.01` with a `tolerance` of `1.0`.
+///
+/// Rounding down keeps the order of the scores, so the rounded bound of the inner metric is still a
+/// lower bound.
+///
+/// [`Lexicographic`]: crate::metrics::Lexicographic
+#[derive(Clone, Copy, Debug)]
+pub struct Quantized<M> {
+    /// The metric whose scores are rounded. It also decides the change output.
+    pub metric: M,
+    /// The multiple the scores are rounded down to. Must be greater than zero.
+    pub tolerance: f32,
+}
+
+impl<M> Quantized<M> {
+    fn quantize(&self, score: Ordf32) -> Ordf32 {
+        debug_assert!(self.tolerance > 0.0);
+        Ordf32(FloatExt::floor(score.0 / self.tolerance) * self.tolerance)
+    }
+}
+
+impl<M> BnbMetric for Quantized<M>
+where
+    M: BnbMetric<Score = Ordf32>,
+{
+    type Score = Ordf32;
+
+    fn drain(&mut self, cs: &CoinSelector<'_>, target: Target) -> Drain {
+        self.metric.drain(cs, target)
+    }
+
+    fn score(&mut self, cs: &CoinSelector<'_>, target: Target) -> Option<Ordf32> {
+        let score = self.metric.score(cs, target)?;
+        Some(self.quantize(score))
+    }
+
+    fn bound(&mut self, cs: &CoinSelector<'_>, target: Target) -> Option<Ordf32> {
+        let bound = self.metric.bound(cs, target)?;
+        Some(self.quantize(bound))
+    }
+
+    fn requires_ordering_by_descending_value_pwu(&self) -> bool {
+        self.metric.requires_ordering_by_descending_value_pwu()
+    }
+}
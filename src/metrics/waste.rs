@@ -0,0 +1,113 @@
+use super::{can_meet_constraints, input_waste_lower_bound, meets_constraints};
+use crate::{float::Ordf32, BnbMetric, CoinSelector, Drain, DrainWeights, FeeRate, Target};
+
+/// Metric that aims to minimize the [waste metric] of the selection, as Bitcoin Core does.
+///
+/// The waste of a selection is:
+///
+/// > `input_weight * (feerate - long_term_feerate) + change_or_excess_cost`
+///
+/// where `change_or_excess_cost` is the cost of creating and later spending the change output if
+/// there is one, or the excess (scaled by `excess_discount`) if there isn't.
+///
+/// Like [`LowestFee`], `Waste` decides for itself whether a selection should have a change output:
+/// change is added whenever it has less waste than giving the excess to the miners and the change
+/// would not be dust.
+///
+/// [waste metric]: https://bitcoin.stackexchange.com/questions/113622/what-does-waste-metric-mean-in-the-context-of-coin-selection
+/// [`LowestFee`]: crate::metrics::LowestFee
+#[derive(Clone, Copy, Debug)]
+pub struct Waste {
+    /// The estimated feerate needed to spend our inputs and change output later.
+    pub long_term_feerate: FeeRate,
+    /// The feerate used to determine the dust threshold of the change output.
+    pub dust_relay_feerate: FeeRate,
+    /// The weights of the change output that would be added.
+    pub drain_weights: DrainWeights,
+    /// How much of the excess of a changeless selection counts as waste. Must be between `0.0` and
+    /// `1.0` where `1.0` gives no discount. See [`CoinSelector::waste`].
+    pub excess_discount: f32,
+}
+
+impl Waste {
+    /// The value the change output should have, or `None` if this selection should be changeless.
+    fn drain_value(&self, cs: &CoinSelector<'_>, target: Target) -> Option<u64> {
+        let excess_with_drain_weight = cs.excess(
+            target,
+            Drain {
+                weights: self.drain_weights,
+                value: 0,
+            },
+        );
+        if excess_with_drain_weight <= 0 {
+            return None;
+        }
+
+        // Change is only worth adding if creating and spending it wastes less than giving the
+        // (discounted) excess to the miners.
+        let waste_with_change = self.drain_weights.waste(
+            target.fee.rate,
+            self.long_term_feerate,
+            target.outputs.n_outputs,
+        );
+        let waste_without_change =
+            cs.excess(target, Drain::NONE).max(0) as f32 * self.excess_discount.clamp(0.0, 1.0);
+        if waste_with_change >= waste_without_change {
+            return None;
+        }
+
+        let dust_threshold = self.drain_weights.dust_threshold(self.dust_relay_feerate);
+        if excess_with_drain_weight < dust_threshold as i64 {
+            return None;
+        }
+
+        // Same as `LowestFee`: never add a change output that pushes the tx over `max_weight`.
+        if !cs.is_within_max_weight(target, self.drain_weights) {
+            return None;
+        }
+
+        Some(excess_with_drain_weight.unsigned_abs())
+    }
+}
+
+impl BnbMetric for Waste {
+    type Score = Ordf32;
+
+    fn drain(&mut self, cs: &CoinSelector<'_>, target: Target) -> Drain {
+        self.drain_value(cs, target)
+            .map_or(Drain::NONE, |value| Drain {
+                weights: self.drain_weights,
+                value,
+            })
+    }
+
+    fn score(&mut self, cs: &CoinSelector<'_>, target: Target) -> Option<Ordf32> {
+        if !cs.is_funded(target) || !meets_constraints(cs, target) {
+            return None;
+        }
+        let drain = self.drain(cs, target);
+        if !cs.is_within_max_weight(target, drain.weights) {
+            return None;
+        }
+        Some(Ordf32(cs.waste(
+            target,
+            self.long_term_feerate,
+            drain,
+            self.excess_discount,
+        )))
+    }
+
+    fn bound(&mut self, cs: &CoinSelector<'_>, target: Target) -> Option<Ordf32> {
+        if !can_meet_constraints(cs, target, DrainWeights::NONE) {
+            return None;
+        }
+
+        // The change or excess part of the waste is never negative, so the input waste of the best
+        // descendant is a lower bound on its total waste.
+        input_waste_lower_bound(cs, target, self.long_term_feerate).map(Ordf32)
+    }
+
+    fn requires_ordering_by_descending_value_pwu(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,67 @@
+use crate::{float::Ordf32, BnbMetric, CoinSelector, Drain, Target};
+
+/// Combines two metrics by minimizing the weighted sum of their scores.
+///
+/// This lets you trade off one objective against another, e.g. the fee against how many inputs
+/// are spent. For a strict order of objectives use [`Lexicographic`] instead.
+///
+/// A selection is only valid if both metrics give it a score. The two metrics may disagree about
+/// whether a selection should have change, so the change output is decided by the `primary`
+/// metric alone.
+///
+/// The bound is the weighted sum of the inner bounds. This is only a valid lower bound if both
+/// weights are zero or above.
+///
+/// Both metrics must score with [`Ordf32`] so that their scores can be summed. [`Lexicographic`]
+/// combines metrics with any type of score.
+///
+/// [`Lexicographic`]: crate::metrics::Lexicographic
+#[derive(Clone, Copy, Debug)]
+pub struct Weighted<P, S> {
+    /// The metric that decides the change output.
+    pub primary: P,
+    /// How much the `primary` score counts towards the combined score. Must not be negative.
+    pub primary_weight: f32,
+    /// The metric traded off against the `primary` metric.
+    pub secondary: S,
+    /// How much the `secondary` score counts towards the combined score. Must not be negative.
+    pub secondary_weight: f32,
+}
+
+impl<P, S> Weighted<P, S> {
+    fn combine(&self, primary: Ordf32, secondary: Ordf32) -> Ordf32 {
+        debug_assert!(self.primary_weight >= 0.0 && self.secondary_weight >= 0.0);
+        Ordf32(primary.0 * self.primary_weight + secondary.0 * self.secondary_weight)
+    }
+}
+
+impl<P, S> BnbMetric for Weighted<P, S>
+where
+    P: BnbMetric<Score = Ordf32>,
+    S: BnbMetric<Score = Ordf32>,
+{
+    type Score = Ordf32;
+
+    fn drain(&mut self, cs: &CoinSelector<'_>, target: Target) -> Drain {
+        self.primary.drain(cs, target)
+    }
+
+    fn score(&mut self, cs: &CoinSelector<'_>, target: Target) -> Option<Ordf32> {
+        let primary = self.primary.score(cs, target)?;
+        let secondary = self.secondary.score(cs, target)?;
+        Some(self.combine(primary, secondary))
+    }
+
+    fn bound(&mut self, cs: &CoinSelector<'_>, target: Target) -> Option<Ordf32> {
+        // each inner bound is no more than the inner score of any descendant, and with non-negative
+        // weights neither is their weighted sum
+        let primary = self.primary.bound(cs, target)?;
+        let secondary = self.secondary.bound(cs, target)?;
+        Some(self.combine(primary, secondary))
+    }
+
+    fn requires_ordering_by_descending_value_pwu(&self) -> bool {
+        self.primary.requires_ordering_by_descending_value_pwu()
+            || self.secondary.requires_ordering_by_descending_value_pwu()
+    }
+}
@@ -0,0 +1,115 @@
+use crate::{float::Ordf32, CoinSelector};
+
+/// Gets told about everything branch and bound does, e.g. to trace or diagnose a slow search.
+///
+/// All the methods do nothing by default, so you only need to implement the ones you're interested
+/// in. `()` is the observer that ignores everything and [`BnbStats`] counts the events. Pass one to
+/// [`CoinSelector::run_bnb_with`] or [`CoinSelector::bnb_solutions_with`] in [`BnbOptions`].
+///
+/// The observer is told the lower bounds and scores of the [`BnbMetric`] in use, which are
+/// [`Ordf32`] for the built-in metrics. `()` and [`BnbStats`] work with any score.
+///
+/// [`BnbOptions`]: crate::BnbOptions
+/// [`BnbMetric`]: crate::BnbMetric
+pub trait BnbObserver<S = Ordf32> {
+    /// A branch with `lower_bound` was added to the queue, which now has `queue_len` branches.
+    fn pushed(&mut self, _cs: &CoinSelector<'_>, _lower_bound: S, _queue_len: usize) {}
+
+    /// A branch with `lower_bound` was taken off the queue to be scored and expanded. This starts a
+    /// new round.
+    fn popped(&mut self, _cs: &CoinSelector<'_>, _lower_bound: S) {}
+
+    /// A branch was not added to the queue because its `lower_bound` is no better than the best
+    /// score found so far, or because the queue was full of branches that are at least as good.
+    fn pruned(&mut self, _cs: &CoinSelector<'_>, _lower_bound: S) {}
+
+    /// A queued branch with `lower_bound` was removed without being expanded, either to make room
+    /// for a better branch in a full queue or because the best score improved after it was queued
+    /// so it can't beat it anymore. When exploring best first, the search ends by dropping every
+    /// branch left in the queue this way.
+    fn dropped(&mut self, _cs: &CoinSelector<'_>, _lower_bound: S) {}
+
+    /// A branch was not added to the queue because the metric found it has no solutions (its bound
+    /// is `None`).
+    fn no_bound(&mut self, _cs: &CoinSelector<'_>) {}
+
+    /// A selection with a better `score` than any before it was found.
+    fn improved(&mut self, _cs: &CoinSelector<'_>, _score: S) {}
+}
+
+impl<S> BnbObserver<S> for () {}
+
+impl<S, O: BnbObserver<S> + ?Sized> BnbObserver<S> for &mut O {
+    fn pushed(&mut self, cs: &CoinSelector<'_>, lower_bound: S, queue_len: usize) {
+        (**self).pushed(cs, lower_bound, queue_len)
+    }
+
+    fn popped(&mut self, cs: &CoinSelector<'_>, lower_bound: S) {
+        (**self).popped(cs, lower_bound)
+    }
+
+    fn pruned(&mut self, cs: &CoinSelector<'_>, lower_bound: S) {
+        (**self).pruned(cs, lower_bound)
+    }
+
+    fn dropped(&mut self, cs: &CoinSelector<'_>, lower_bound: S) {
+        (**self).dropped(cs, lower_bound)
+    }
+
+    fn no_bound(&mut self, cs: &CoinSelector<'_>) {
+        (**self).no_bound(cs)
+    }
+
+    fn improved(&mut self, cs: &CoinSelector<'_>, score: S) {
+        (**self).improved(cs, score)
+    }
+}
+
+/// A [`BnbObserver`] that collects statistics about the search.
+///
+/// Pass it by `&mut` so you can read it after the search.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BnbStats {
+    /// The number of rounds, i.e. branches taken off the queue and expanded.
+    pub rounds: usize,
+    /// The number of branches added to the queue.
+    pub pushed: usize,
+    /// The number of branches that were not added to the queue, either because their bound was no
+    /// better than the best score or because they have no solutions.
+    pub pruned: usize,
+    /// The number of branches that were removed from the queue without being expanded. Every
+    /// branch that was [`pushed`](Self::pushed) is counted in exactly one of `rounds`, `dropped`
+    /// or the branches left in the queue, and a search that finished leaves none in the queue.
+    pub dropped: usize,
+    /// The most branches that were in the queue at once.
+    pub peak_queue_len: usize,
+    /// The round the best solution was found in, if any.
+    pub rounds_to_best: Option<usize>,
+}
+
+impl<S> BnbObserver<S> for BnbStats {
+    fn pushed(&mut self, _cs: &CoinSelector<'_>, _lower_bound: S, queue_len: usize) {
+        self.pushed += 1;
+        self.peak_queue_len = self.peak_queue_len.max(queue_len);
+    }
+
+    fn popped(&mut self, _cs: &CoinSelector<'_>, _lower_bound: S) {
+        self.rounds += 1;
+    }
+
+    fn pruned(&mut self, _cs: &CoinSelector<'_>, _lower_bound: S) {
+        self.pruned += 1;
+    }
+
+    fn dropped(&mut self, _cs: &CoinSelector<'_>, _lower_bound: S) {
+        self.dropped += 1;
+    }
+
+    fn no_bound(&mut self, _cs: &CoinSelector<'_>) {
+        self.pruned += 1;
+    }
+
+    fn improved(&mut self, _cs: &CoinSelector<'_>, _score: S) {
+        self.rounds_to_best = Some(self.rounds);
+    }
+}
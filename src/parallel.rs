@@ -0,0 +1,330 @@
+use crate::{
+    bnb::split, BnbBudget, BnbMetric, BnbProgress, BudgetLimit, Candidate, CoinSelector,
+    DetachedSelector, Drain, NoBnbSolution, Target,
+};
+use alloc::{collections::BinaryHeap, sync::Arc, vec::Vec};
+use core::cmp::Reverse;
+use std::sync::{Condvar, Mutex, PoisonError};
+
+const POISONED: &str = "a bnb thread panicked";
+
+impl CoinSelector<'_> {
+    /// Run branch and bound like [`run_bnb`] but on `threads` threads (requires the `std`
+    /// feature).
+    ///
+    /// The threads take branches off one shared queue, best first, and bound the branches they
+    /// lead to in parallel. The best score found so far is shared between the threads so they
+    /// prune just as much as a single thread would. The solution has the same score as the one
+    /// [`run_bnb`] finds, but if several solutions have that score it may return a different one.
+    ///
+    /// The `budget` is shared by the threads, e.g. a round limit counts the rounds of every thread.
+    /// It stays on the calling thread, which lets the threads start one round at a time, so it can
+    /// borrow local state like a `&AtomicBool` cancel flag. The candidates are copied so that the
+    /// threads can share them and each thread gets its own clone of the `metric`.
+    ///
+    /// With `threads` of `0` or `1`, this is the same as [`run_bnb`].
+    ///
+    /// [`run_bnb`]: Self::run_bnb
+    pub fn run_bnb_parallel<M, B>(
+        &mut self,
+        target: Target,
+        mut metric: M,
+        mut budget: B,
+        threads: usize,
+    ) -> Result<(M::Score, Drain), NoBnbSolution>
+    where
+        M: BnbMetric + Clone + Send + 'static,
+        M::Score: Send,
+        B: BnbBudget,
+    {
+        if threads <= 1 {
+            return self.run_bnb(target, metric, budget);
+        }
+
+        let mut root = self.clone();
+        if metric.requires_ordering_by_descending_value_pwu() {
+            root.sort_candidates_by_descending_value_pwu();
+        }
+        let mut queue = BinaryHeap::new();
+        if root.can_conflict() {
+            if let Some(lower_bound) = metric.bound(&root, target) {
+                queue.push(Subtree {
+                    lower_bound,
+                    selector: root.detach(),
+                    is_exclusion: false,
+                });
+            }
+        }
+
+        let has_root = !queue.is_empty();
+        let search = Arc::new(Search {
+            state: Mutex::new(SearchState {
+                queue,
+                busy: 0,
+                best: None,
+                permits: 0,
+                progress: BnbProgress {
+                    rounds: 0,
+                    nodes: 1,
+                },
+                exhausted: None,
+                panicked: false,
+            }),
+            changed: Condvar::new(),
+            best_score: Mutex::new(None),
+        });
+        let candidates = Arc::new(
+            (0..self.candidates().len())
+                .map(|index| self.candidate(index))
+                .collect::<Vec<_>>(),
+        );
+
+        // Without a root there is nothing to search, so don't start any threads.
+        if has_root {
+            let handles = (0..threads)
+                .map(|_| {
+                    let search = search.clone();
+                    let candidates = candidates.clone();
+                    let metric = metric.clone();
+                    std::thread::spawn(move || search.work(&candidates, target, metric))
+                })
+                .collect::<Vec<_>>();
+            search.hand_out_rounds(&mut budget);
+            for handle in handles {
+                if let Err(panic) = handle.join() {
+                    std::panic::resume_unwind(panic);
+                }
+            }
+        }
+
+        let mut state = search.state.lock().expect(POISONED);
+        if let Some((score, selector)) = state.best.take() {
+            self.reattach(selector);
+            let drain = metric.drain(self, target);
+            return Ok((score, drain));
+        }
+        if let Some(limit) = state.exhausted {
+            return Err(NoBnbSolution::BudgetExhausted {
+                limit,
+                progress: state.progress,
+            });
+        }
+        if !self.is_fundable(target) {
+            return Err(NoBnbSolution::InsufficientFunds);
+        }
+        if !self.can_conflict() {
+            return Err(NoBnbSolution::NoConflict);
+        }
+        Err(self.exceeded_limit(target))
+    }
+}
+
+/// A branch and bound search shared by several threads.
+#[derive(Debug)]
+struct Search<S> {
+    state: Mutex<SearchState<S>>,
+    /// Notified whenever branches are added to the queue or the search ends.
+    changed: Condvar,
+    /// The best score so far, which can be read without taking the lock of the `state`.
+    best_score: Mutex<Option<S>>,
+}
+
+#[derive(Debug)]
+struct SearchState<S> {
+    queue: BinaryHeap<Subtree<S>>,
+    /// The number of threads that are working on a branch they took off the queue.
+    busy: usize,
+    best: Option<(S, DetachedSelector)>,
+    /// The number of rounds the budget allows the threads to start before it is checked again.
+    permits: usize,
+    progress: BnbProgress,
+    exhausted: Option<BudgetLimit>,
+    /// Set once a thread panicked, which ends the search so the panic reaches the caller.
+    panicked: bool,
+}
+
+impl<S: Ord + Copy> Search<S> {
+    /// Searches branches until the search ends.
+    fn work<M: BnbMetric<Score = S>>(
+        &self,
+        candidates: &[Candidate],
+        target: Target,
+        mut metric: M,
+    ) {
+        while let Some(subtree) = self.next_subtree() {
+            let busy = Busy(self);
+            let selector = subtree.selector.attach(candidates);
+
+            let solution = if subtree.is_exclusion || !selector.is_conflicting() {
+                // an exclusion branch has the same selection as its parent, which has been scored
+                // already
+                None
+            } else {
+                metric
+                    .score(&selector, target)
+                    .filter(|&score| self.improve(score))
+                    .map(|score| (score, selector.detach()))
+            };
+
+            let mut nodes = 0;
+            let mut children = Vec::with_capacity(2);
+            if let Some((inclusion, exclusion)) = split(&selector) {
+                for (child, is_exclusion) in [(inclusion, false), (exclusion, true)] {
+                    nodes += 1;
+                    if !child.can_conflict() {
+                        continue;
+                    }
+                    let lower_bound = match metric.bound(&child, target) {
+                        Some(lower_bound) => lower_bound,
+                        None => continue,
+                    };
+                    if self.best_score().map_or(true, |best| best > lower_bound) {
+                        children.push(Subtree {
+                            lower_bound,
+                            selector: child.detach(),
+                            is_exclusion,
+                        });
+                    }
+                }
+            }
+
+            let mut state = self.state.lock().expect(POISONED);
+            state.progress.nodes += nodes;
+            if let Some((score, selector)) = solution {
+                if state.best.as_ref().map_or(true, |(best, _)| score < *best) {
+                    state.best = Some((score, selector));
+                }
+            }
+            state.queue.extend(children);
+            drop(state);
+            drop(busy);
+        }
+    }
+
+    /// The best score found by any of the threads so far.
+    fn best_score(&self) -> Option<S> {
+        *self.best_score.lock().expect(POISONED)
+    }
+
+    /// Returns whether `score` is better than the best so far, and if so makes it the best.
+    fn improve(&self, score: S) -> bool {
+        let mut best = self.best_score.lock().expect(POISONED);
+        if best.map_or(true, |best| score < best) {
+            *best = Some(score);
+            return true;
+        }
+        false
+    }
+
+    /// Checks the `budget` before every round the threads start until the search ends.
+    ///
+    /// This runs on the calling thread so the budget never has to be sent to the other threads.
+    fn hand_out_rounds<B: BnbBudget>(&self, budget: &mut B) {
+        let mut state = self.state.lock().expect(POISONED);
+        while state.exhausted.is_none() && !state.panicked {
+            let viable = state.queue.peek().map_or(false, |subtree| {
+                self.best_score()
+                    .map_or(true, |best| best > subtree.lower_bound)
+            });
+            if !viable && state.busy == 0 {
+                // Nothing is queued and nothing more will be.
+                break;
+            }
+            if viable && state.permits == 0 {
+                let progress = state.progress;
+                match budget.check(progress) {
+                    Some(limit) => state.exhausted = Some(limit),
+                    None => state.permits = 1,
+                }
+                self.changed.notify_all();
+                continue;
+            }
+            state = self.changed.wait(state).expect(POISONED);
+        }
+    }
+
+    /// Waits for the best branch on the queue that the budget allows a round for, or returns
+    /// `None` once the search has ended.
+    fn next_subtree(&self) -> Option<Subtree<S>> {
+        let mut state = self.state.lock().expect(POISONED);
+        loop {
+            if state.exhausted.is_some() || state.panicked {
+                return None;
+            }
+            if let Some(subtree) = state.queue.peek() {
+                if self
+                    .best_score()
+                    .map_or(true, |best| best > subtree.lower_bound)
+                {
+                    if state.permits > 0 {
+                        state.permits -= 1;
+                        state.progress.rounds += 1;
+                        state.busy += 1;
+                        self.changed.notify_all();
+                        return state.queue.pop();
+                    }
+                } else {
+                    // Nothing on the queue can beat the best anymore.
+                    state.queue.clear();
+                }
+            }
+            if state.queue.is_empty() && state.busy == 0 {
+                // Nothing is queued and nothing more will be.
+                return None;
+            }
+            state = self.changed.wait(state).expect(POISONED);
+        }
+    }
+}
+
+/// Marks a thread as working on a branch it took off the queue of a [`Search`] until dropped.
+///
+/// This is dropped even if the thread panics so that the other threads don't wait for it forever.
+struct Busy<'s, S>(&'s Search<S>);
+
+impl<S> Drop for Busy<'_, S> {
+    fn drop(&mut self) {
+        let mut state = self.0.state.lock().unwrap_or_else(PoisonError::into_inner);
+        state.busy -= 1;
+        if std::thread::panicking() {
+            state.panicked = true;
+        }
+        drop(state);
+        self.0.changed.notify_all();
+    }
+}
+
+/// A queued branch of a [`Search`].
+#[derive(Debug)]
+struct Subtree<S> {
+    lower_bound: S,
+    selector: DetachedSelector,
+    is_exclusion: bool,
+}
+
+impl<S: Ord + Copy> Subtree<S> {
+    /// The lowest lower bound first, then inclusion branches like [`CoinSelector::bnb_solutions`].
+    fn priority(&self) -> (Reverse<S>, bool) {
+        (Reverse(self.lower_bound), !self.is_exclusion)
+    }
+}
+
+impl<S: Ord + Copy> Ord for Subtree<S> {
+    fn cmp(&self, other: &Self) -> core::cmp::Ordering {
+        self.priority().cmp(&other.priority())
+    }
+}
+
+impl<S: Ord + Copy> PartialOrd for Subtree<S> {
+    fn partial_cmp(&self, other: &Self) -> Option<core::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<S: Ord + Copy> PartialEq for Subtree<S> {
+    fn eq(&self, other: &Self) -> bool {
+        self.priority() == other.priority()
+    }
+}
+
+impl<S: Ord + Copy> Eq for Subtree<S> {}
@@ -0,0 +1,29 @@
+/// A source of randomness for the randomized selection strategies.
+///
+/// This crate has no dependencies so you have to bring your own. Any `FnMut() -> u64` is a
+/// `RandomSource`, so with the `rand` crate you can pass `&mut || rng.next_u64()`.
+pub trait RandomSource {
+    /// Returns a uniformly random `u64`.
+    fn next_u64(&mut self) -> u64;
+}
+
+impl<F: FnMut() -> u64> RandomSource for F {
+    fn next_u64(&mut self) -> u64 {
+        self()
+    }
+}
+
+/// A random index in `0..len`. `len` must not be zero.
+pub(crate) fn random_index<R: RandomSource + ?Sized>(rng: &mut R, len: usize) -> usize {
+    debug_assert!(len > 0);
+    // Lemire's multiply-shift: the bias is negligible for the lengths we deal with.
+    ((rng.next_u64() as u128 * len as u128) >> 64) as usize
+}
+
+/// Fisher-Yates shuffle.
+pub(crate) fn shuffle<T, R: RandomSource + ?Sized>(rng: &mut R, items: &mut [T]) {
+    for i in (1..items.len()).rev() {
+        let j = random_index(rng, i + 1);
+        items.swap(i, j);
+    }
+}
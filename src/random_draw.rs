@@ -0,0 +1,103 @@
+use crate::{
+    float::Ordf32, random::shuffle, ChangePolicy, CoinSelector, Drain, InsufficientFunds,
+    RandomSource, SelectError, Target,
+};
+use alloc::vec::Vec;
+
+impl CoinSelector<'_> {
+    /// Select candidates with Single Random Draw (SRD) like Bitcoin Core.
+    ///
+    /// The candidates are shuffled and selected one by one until the `target` is met with enough
+    /// left over for a change output of `change_policy.min_value`. Whenever the selection goes over
+    /// [`Target::max_weight`], the selected candidates with the lowest effective value are dropped
+    /// until it fits again. Candidates that don't pay for themselves at `target.fee.rate` are never
+    /// selected, and neither are candidates the TRUC policy doesn't allow with the ones selected
+    /// before them (see [`is_truc_compatible`]).
+    ///
+    /// Picking inputs at random avoids fingerprinting the wallet by its selections. Returns the
+    /// change output according to `change_policy` (see [`drain`]).
+    ///
+    /// # Errors
+    ///
+    /// - [`SelectError::MaxWeightExceeded`] if candidates had to be dropped to stay within
+    ///   [`Target::max_weight`] and the rest couldn't meet the target.
+    /// - [`SelectError::TrucPolicyViolated`] if the candidates couldn't meet the target because
+    ///   the TRUC policy doesn't allow spending them together.
+    /// - [`SelectError::InsufficientFunds`] otherwise if the candidates can't meet the target.
+    ///
+    /// The selection is left unchanged if there is an error.
+    ///
+    /// [`drain`]: Self::drain
+    /// [`is_truc_compatible`]: Self::is_truc_compatible
+    pub fn select_random_draw<R: RandomSource>(
+        &mut self,
+        target: Target,
+        change_policy: ChangePolicy,
+        rng: &mut R,
+    ) -> Result<Drain, SelectError> {
+        let with_change = Drain {
+            weights: change_policy.drain_weights,
+            value: change_policy.min_value,
+        };
+
+        let mut order = self
+            .unselected()
+            .filter(|(_, c)| c.effective_value(target.fee.rate) > 0.0)
+            .map(|(index, _)| index)
+            .collect::<Vec<_>>();
+        shuffle(rng, &mut order);
+
+        if !self.is_truc_compatible(target) {
+            return Err(SelectError::TrucPolicyViolated);
+        }
+        if self.is_funded_with_drain(target, with_change)
+            && self.is_within_max_weight(target, change_policy.drain_weights)
+        {
+            return Ok(self.drain(target, change_policy));
+        }
+
+        let mut selection = self.clone();
+        let mut drawn = Vec::new();
+        let mut max_weight_exceeded = false;
+        let mut truc_policy_violated = false;
+        for index in order {
+            selection.select(index);
+            if !selection.is_truc_compatible(target) {
+                truc_policy_violated = true;
+                selection.deselect(index);
+                continue;
+            }
+            drawn.push(index);
+
+            while !drawn.is_empty()
+                && !selection.is_within_max_weight(target, change_policy.drain_weights)
+            {
+                max_weight_exceeded = true;
+                let (position, _) = drawn
+                    .iter()
+                    .enumerate()
+                    .min_by_key(|(_, &index)| {
+                        Ordf32(self.candidate(index).effective_value(target.fee.rate))
+                    })
+                    .expect("not empty");
+                selection.deselect(drawn.swap_remove(position));
+            }
+
+            if selection.is_funded_with_drain(target, with_change) {
+                let drain = selection.drain(target, change_policy);
+                *self = selection;
+                return Ok(drain);
+            }
+        }
+
+        if max_weight_exceeded {
+            return Err(SelectError::MaxWeightExceeded);
+        }
+        if truc_policy_violated {
+            return Err(SelectError::TrucPolicyViolated);
+        }
+        Err(SelectError::InsufficientFunds(InsufficientFunds {
+            missing: selection.excess(target, with_change).unsigned_abs(),
+        }))
+    }
+}
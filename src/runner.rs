@@ -0,0 +1,252 @@
+use crate::{
+    float::Ordf32,
+    metrics::{Changeless, LowestFee},
+    BnbMetric, ChangePolicy, CoinSelector, Drain, DrainWeights, NoBnbSolution, RandomSource,
+    SelectError, Target,
+};
+use alloc::vec::Vec;
+
+/// A coin selection algorithm that [`SelectionRunner`] can run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Strategy {
+    /// Branch and bound with the runner's [`LowestFee`] metric.
+    LowestFeeBnb,
+    /// Branch and bound with the runner's [`LowestFee`] metric constrained to changeless solutions
+    /// (see [`Changeless`]).
+    ChangelessBnb,
+    /// Select the candidates with the largest value first until the target is met.
+    LargestFirst,
+    /// Select candidates in a random order until the target is met with change (see
+    /// [`CoinSelector::select_random_draw`]).
+    RandomDraw,
+}
+
+impl Strategy {
+    /// All the strategies, in the order [`SelectionRunner`] tries them by default.
+    pub const ALL: [Strategy; 4] = [
+        Strategy::LowestFeeBnb,
+        Strategy::ChangelessBnb,
+        Strategy::LargestFirst,
+        Strategy::RandomDraw,
+    ];
+}
+
+/// How [`SelectionRunner`] compares the selections of different strategies. Lower is better.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    /// The [waste] of the selection at the runner's long-term feerate (with no excess discount).
+    ///
+    /// [waste]: CoinSelector::waste
+    Waste,
+    /// The fee of the transaction plus the fee to spend its change output at the runner's
+    /// long-term feerate. This is what [`LowestFee`] minimizes.
+    LongTermFee,
+}
+
+/// Runs several coin selection strategies over the same selection and target and picks the best
+/// result, like Bitcoin Core's `SelectCoins`.
+///
+/// Every strategy uses `metric` to decide whether its selection gets a change output, so their
+/// results are compared on equal terms.
+#[derive(Debug, Clone)]
+pub struct SelectionRunner {
+    /// The metric used by the branch and bound strategies and to decide the change output of the
+    /// others.
+    pub metric: LowestFee,
+    /// How the results of the strategies are compared.
+    pub comparison: Comparison,
+    /// The strategies to run. If two of them tie, the one that comes first wins.
+    pub strategies: Vec<Strategy>,
+    /// The maximum number of rounds each branch and bound strategy runs for.
+    pub max_rounds: usize,
+}
+
+/// The result of one strategy run by [`SelectionRunner`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Attempt {
+    /// The strategy that was run.
+    pub strategy: Strategy,
+    /// The [`Comparison`] score of its selection, or why it failed to find one.
+    pub result: Result<Ordf32, StrategyError>,
+}
+
+/// The winning selection of [`SelectionRunner::run`].
+#[derive(Debug, Clone)]
+pub struct RunnerSelection<'a> {
+    /// The strategy that found the selection.
+    pub strategy: Strategy,
+    /// The selection.
+    pub selector: CoinSelector<'a>,
+    /// The change output of the selection.
+    pub drain: Drain,
+    /// The [`Comparison`] score of the selection. No other attempt scored lower.
+    pub score: Ordf32,
+    /// Every strategy that was run, in order, with its score or error.
+    pub attempts: Vec<Attempt>,
+}
+
+/// Why a strategy run by [`SelectionRunner`] failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StrategyError {
+    /// A branch and bound strategy found no solution.
+    Bnb(NoBnbSolution),
+    /// A greedy or random strategy couldn't select enough.
+    Select(SelectError),
+    /// A greedy or random strategy selected enough but none of the [`CoinSelector::conflicts`].
+    NoConflict,
+}
+
+impl core::fmt::Display for StrategyError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            StrategyError::Bnb(e) => write!(f, "{}", e),
+            StrategyError::Select(e) => write!(f, "{}", e),
+            StrategyError::NoConflict => write!(f, "the selection spends none of the conflicts"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for StrategyError {}
+
+/// Error returned by [`SelectionRunner::run`] when none of the strategies found a selection.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NoSelection {
+    /// Every strategy that was run, in order, with its error.
+    pub attempts: Vec<Attempt>,
+}
+
+impl core::fmt::Display for NoSelection {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "no strategy found a selection")?;
+        for attempt in &self.attempts {
+            if let Err(e) = attempt.result {
+                write!(f, "; {:?}: {}", attempt.strategy, e)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for NoSelection {}
+
+impl SelectionRunner {
+    /// Runs every strategy on `cs` for `target` and returns the selection with the lowest
+    /// [`Comparison`] score. `rng` is only used by [`Strategy::RandomDraw`].
+    pub fn run<'a, R: RandomSource>(
+        &self,
+        cs: &CoinSelector<'a>,
+        target: Target,
+        rng: &mut R,
+    ) -> Result<RunnerSelection<'a>, NoSelection> {
+        let mut attempts = Vec::with_capacity(self.strategies.len());
+        let mut best = Option::<(Strategy, CoinSelector<'a>, Drain, Ordf32)>::None;
+
+        for &strategy in &self.strategies {
+            let result = self
+                .select(strategy, cs, target, rng)
+                .map(|(selector, drain)| {
+                    let score = self.compare(&selector, target, drain);
+                    if best.as_ref().map_or(true, |(_, _, _, best)| score < *best) {
+                        best = Some((strategy, selector, drain, score));
+                    }
+                    score
+                });
+            attempts.push(Attempt { strategy, result });
+        }
+
+        match best {
+            Some((strategy, selector, drain, score)) => Ok(RunnerSelection {
+                strategy,
+                selector,
+                drain,
+                score,
+                attempts,
+            }),
+            None => Err(NoSelection { attempts }),
+        }
+    }
+
+    fn select<'a, R: RandomSource>(
+        &self,
+        strategy: Strategy,
+        cs: &CoinSelector<'a>,
+        target: Target,
+        rng: &mut R,
+    ) -> Result<(CoinSelector<'a>, Drain), StrategyError> {
+        let mut selector = cs.clone();
+        let drain = match strategy {
+            Strategy::LowestFeeBnb => {
+                selector
+                    .run_bnb(target, self.metric.clone(), self.max_rounds)
+                    .map_err(StrategyError::Bnb)?
+                    .1
+            }
+            Strategy::ChangelessBnb => {
+                selector
+                    .run_bnb(target, Changeless(self.metric.clone()), self.max_rounds)
+                    .map_err(StrategyError::Bnb)?
+                    .1
+            }
+            Strategy::LargestFirst => {
+                selector.sort_candidates_by_key(|(_, c)| core::cmp::Reverse(c.value));
+                selector
+                    .select_until_target_met(target)
+                    .map_err(StrategyError::Select)?;
+                if !selector.is_conflicting() {
+                    return Err(StrategyError::NoConflict);
+                }
+                self.drain(&selector, target)
+            }
+            Strategy::RandomDraw => {
+                selector
+                    .select_random_draw(target, self.random_draw_change_policy(), rng)
+                    .map_err(StrategyError::Select)?;
+                if !selector.is_conflicting() {
+                    return Err(StrategyError::NoConflict);
+                }
+                self.drain(&selector, target)
+            }
+        };
+        Ok((selector, drain))
+    }
+
+    /// The change output the metric decides on for a selection that wasn't found by it.
+    fn drain(&self, cs: &CoinSelector<'_>, target: Target) -> Drain {
+        self.metric.clone().drain(cs, target)
+    }
+
+    /// Random draw aims for enough change that any of the metric's change options would not be
+    /// dust. The metric decides the actual drain afterwards.
+    fn random_draw_change_policy(&self) -> ChangePolicy {
+        let heaviest = self
+            .metric
+            .drain_options
+            .iter()
+            .copied()
+            .max_by_key(|weights| weights.output_weight)
+            .unwrap_or(DrainWeights::NONE);
+        let min_value = self
+            .metric
+            .drain_options
+            .iter()
+            .map(|weights| weights.dust_threshold(self.metric.dust_relay_feerate))
+            .max()
+            .unwrap_or(0);
+        ChangePolicy::min_value(heaviest, min_value)
+    }
+
+    fn compare(&self, cs: &CoinSelector<'_>, target: Target, drain: Drain) -> Ordf32 {
+        match self.comparison {
+            Comparison::Waste => {
+                Ordf32(cs.waste(target, self.metric.long_term_feerate, drain, 1.0))
+            }
+            Comparison::LongTermFee => Ordf32(
+                (cs.fee(target.value(), drain.value)
+                    + drain.weights.spend_fee(self.metric.long_term_feerate) as i64)
+                    as f32,
+            ),
+        }
+    }
+}
@@ -0,0 +1,111 @@
+use crate::{
+    bnb::BnbIter, BnbBudget, BnbMetric, BnbObserver, BnbOptions, BnbProgress, BnbSolution,
+    BudgetLimit, CoinSelector, NoBnbSolution, Target,
+};
+
+/// A branch and bound search that can be stopped and carried on with later.
+///
+/// Unlike [`CoinSelector::run_bnb`], the queue of branches, the best solution and the metric are
+/// kept between calls to [`run`], so each call picks up where the last one stopped. For example,
+/// you can run 10,000 rounds, show the user the result, and then keep searching for a better one.
+/// Create one with [`CoinSelector::bnb_session`].
+///
+/// [`run`]: Self::run
+#[derive(Debug)]
+pub struct BnbSession<'a, M: BnbMetric, O: BnbObserver<M::Score> = ()> {
+    start: CoinSelector<'a>,
+    upper_bound: Option<M::Score>,
+    iter: BnbIter<'a, M, (), O>,
+    best: Option<(CoinSelector<'a>, M::Score)>,
+    finished: bool,
+}
+
+impl<'a> CoinSelector<'a> {
+    /// Start a branch and bound session to minimize the score of the `metric` (see
+    /// [`BnbSession`]).
+    ///
+    /// Nothing is searched until [`BnbSession::run`] is called. The budget is passed to each run
+    /// instead of the `options`. The selection of `self` is left as is.
+    pub fn bnb_session<M: BnbMetric, O: BnbObserver<M::Score>>(
+        &self,
+        target: Target,
+        metric: M,
+        options: BnbOptions<(), O, M::Score>,
+    ) -> BnbSession<'a, M, O> {
+        BnbSession {
+            start: self.clone(),
+            upper_bound: options.upper_bound,
+            iter: BnbIter::new(self.clone(), target, metric, options),
+            best: None,
+            finished: false,
+        }
+    }
+}
+
+impl<'a, M: BnbMetric, O: BnbObserver<M::Score>> BnbSession<'a, M, O> {
+    /// Search until the `budget` runs out or there is nothing left to search, and return the best
+    /// solution found so far, including by earlier runs.
+    ///
+    /// The `budget` is only checked against the progress of this run, e.g. `run(10_000)` does up to
+    /// 10,000 more rounds.
+    ///
+    /// # Errors
+    ///
+    /// If no solution has been found yet, the same errors as [`CoinSelector::run_bnb_with`].
+    pub fn run<B: BnbBudget>(
+        &mut self,
+        budget: B,
+    ) -> Result<BnbSolution<'a, M::Score>, NoBnbSolution> {
+        if !self.finished {
+            let mut budget = SinceStart {
+                budget,
+                start: self.iter.progress,
+            };
+            while let Some(solution) = self.iter.next_with(&mut budget) {
+                if solution.is_some() {
+                    self.best = solution;
+                }
+            }
+            self.finished = self.iter.exhausted.is_none();
+        }
+
+        match self.best_solution() {
+            Some(solution) => Ok(solution),
+            None => Err(self
+                .start
+                .no_bnb_solution(self.iter.target, &self.iter, self.upper_bound)),
+        }
+    }
+
+    /// The best solution found so far, if any.
+    pub fn best_solution(&mut self) -> Option<BnbSolution<'a, M::Score>> {
+        let (selector, score) = self.best.clone()?;
+        Some(self.iter.solution(selector, score))
+    }
+
+    /// Whether there is nothing left to search, in which case the best solution is the best there
+    /// is (or there is no solution at all).
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// How far the search has got over all of the runs.
+    pub fn progress(&self) -> BnbProgress {
+        self.iter.progress
+    }
+}
+
+/// Checks a budget against the progress made since `start`.
+struct SinceStart<B> {
+    budget: B,
+    start: BnbProgress,
+}
+
+impl<B: BnbBudget> BnbBudget for SinceStart<B> {
+    fn check(&mut self, progress: BnbProgress) -> Option<BudgetLimit> {
+        self.budget.check(BnbProgress {
+            rounds: progress.rounds - self.start.rounds,
+            nodes: progress.nodes - self.start.nodes,
+        })
+    }
+}
@@ -10,8 +10,37 @@ pub struct Target {
     /// Maximum allowed weight of the resulting transaction (WU). `None` = unconstrained.
     ///
     /// This is a feasibility constraint on the answer (the sibling of the value target: a lower
-    /// bound on value, this an upper bound on weight). Relevant e.g. for TRUC/BIP-431 packages.
+    /// bound on value, this an upper bound on weight). The [`truc`](Self::truc) limits apply on
+    /// top of it.
     pub max_weight: Option<u64>,
+    /// The fewest inputs the transaction may have, counted with [`Candidate::input_count`]. `None`
+    /// = unconstrained.
+    ///
+    /// E.g. requiring at least two inputs makes it harder to tell which output is the payment.
+    ///
+    /// [`Candidate::input_count`]: crate::Candidate::input_count
+    pub min_input_count: Option<usize>,
+    /// The most inputs the transaction may have, counted with [`Candidate::input_count`]. `None` =
+    /// unconstrained.
+    ///
+    /// Like [`max_weight`](Self::max_weight) this is an upper bound, e.g. for hardware signers that
+    /// can't sign too many inputs.
+    ///
+    /// [`Candidate::input_count`]: crate::Candidate::input_count
+    pub max_input_count: Option<usize>,
+    /// Whether the transaction is TRUC (BIP-431, version 3), so it must follow the TRUC policy.
+    ///
+    /// Its weight is limited to [`TRUC_MAX_WEIGHT`], or [`TRUC_CHILD_MAX_WEIGHT`] if it spends an
+    /// unconfirmed candidate, and it may only spend one unconfirmed candidate, whose
+    /// [`Ancestors::is_truc`] must be `true`. Whether or not this is set, a transaction can only
+    /// spend unconfirmed TRUC outputs if it's TRUC itself. See
+    /// [`CoinSelector::is_truc_compatible`].
+    ///
+    /// [`TRUC_MAX_WEIGHT`]: crate::TRUC_MAX_WEIGHT
+    /// [`TRUC_CHILD_MAX_WEIGHT`]: crate::TRUC_CHILD_MAX_WEIGHT
+    /// [`Ancestors::is_truc`]: crate::Ancestors::is_truc
+    /// [`CoinSelector::is_truc_compatible`]: crate::CoinSelector::is_truc_compatible
+    pub truc: bool,
 }
 
 impl Target {
@@ -0,0 +1,114 @@
+mod common;
+use bdk_coin_select::{
+    metrics::LowestFee, Ancestors, CoinSelector, Drain, DrainWeights, FeeRate, Target,
+};
+use common::{p2wpkh_candidate, p2wpkh_candidate_with_ancestors};
+
+fn target() -> Target {
+    common::single_output_target(50_000, FeeRate::from_sat_per_vb(10.0))
+}
+
+/// A 200 vbyte parent that paid 1 sat/vb.
+const LOW_FEE_PARENT: Ancestors = Ancestors {
+    fee: 200,
+    weight: 800,
+    is_truc: false,
+};
+
+#[test]
+fn bump_fee_lifts_ancestors_to_feerate() {
+    let feerate = FeeRate::from_sat_per_vb(10.0);
+    assert_eq!(LOW_FEE_PARENT.bump_fee(feerate), 2_000 - 200);
+    assert_eq!(LOW_FEE_PARENT.bump_fee(FeeRate::from_sat_per_vb(1.0)), 0);
+    assert_eq!(
+        Ancestors {
+            fee: 5_000,
+            weight: 800,
+            is_truc: false,
+        }
+        .bump_fee(feerate),
+        0,
+        "ancestors that pay more than the feerate don't get a discount"
+    );
+}
+
+#[test]
+fn excess_and_implied_fee_include_bump_fee() {
+    let candidates = vec![
+        p2wpkh_candidate(100_000),
+        p2wpkh_candidate_with_ancestors(100_000, Some(LOW_FEE_PARENT)),
+    ];
+    let target = target();
+    let bump_fee = LOW_FEE_PARENT.bump_fee(target.fee.rate);
+
+    let mut confirmed = CoinSelector::new(&candidates);
+    confirmed.select(0);
+    let mut unconfirmed = CoinSelector::new(&candidates);
+    unconfirmed.select(1);
+
+    assert_eq!(confirmed.bump_fee(target.fee.rate), 0);
+    assert_eq!(unconfirmed.bump_fee(target.fee.rate), bump_fee);
+    assert_eq!(
+        confirmed.excess(target, Drain::NONE) - unconfirmed.excess(target, Drain::NONE),
+        bump_fee as i64
+    );
+    assert_eq!(
+        unconfirmed.implied_fee(target, DrainWeights::NONE)
+            - confirmed.implied_fee(target, DrainWeights::NONE),
+        bump_fee
+    );
+    assert_eq!(
+        confirmed.effective_value(target.fee.rate) - unconfirmed.effective_value(target.fee.rate),
+        bump_fee as i64
+    );
+    assert_eq!(
+        candidates[0].effective_value(target.fee.rate)
+            - candidates[1].effective_value(target.fee.rate),
+        bump_fee as f32
+    );
+}
+
+/// The unconfirmed candidate has a bit more value, but bumping its parent costs much more than that.
+#[test]
+fn lowest_fee_avoids_bumping_low_fee_parent() {
+    let candidates = vec![
+        p2wpkh_candidate_with_ancestors(101_000, Some(LOW_FEE_PARENT)),
+        p2wpkh_candidate(100_000),
+    ];
+    let target = target();
+    let metric = LowestFee {
+        long_term_feerate: FeeRate::from_sat_per_vb(10.0),
+        dust_relay_feerate: FeeRate::from_sat_per_vb(3.0),
+        drain_options: vec![DrainWeights::TR_KEYSPEND],
+    };
+
+    let mut cs = CoinSelector::new(&candidates);
+    let (_score, drain) = cs.run_bnb(target, metric, 10_000).expect("finds solution");
+    assert_eq!(cs.selected_indices().iter().collect::<Vec<_>>(), vec![1]);
+    assert!(cs.is_funded_with_drain(target, drain));
+}
+
+/// The candidates only differ in their ancestors, so branch and bound mustn't treat them as the same.
+#[test]
+fn lowest_fee_tells_apart_candidates_that_only_differ_in_ancestors() {
+    let parent = Ancestors {
+        fee: 0,
+        weight: 40_000,
+        is_truc: false,
+    };
+    let candidates = vec![
+        p2wpkh_candidate_with_ancestors(100_000, Some(parent)),
+        p2wpkh_candidate(100_000),
+    ];
+    let target = target();
+    let metric = LowestFee {
+        long_term_feerate: FeeRate::from_sat_per_vb(10.0),
+        dust_relay_feerate: FeeRate::from_sat_per_vb(3.0),
+        drain_options: vec![DrainWeights::TR_KEYSPEND],
+    };
+
+    let mut cs = CoinSelector::new(&candidates);
+    cs.run_bnb(target, metric, usize::MAX)
+        .expect("finds solution");
+    assert_eq!(cs.selected_indices().iter().collect::<Vec<_>>(), vec![1]);
+}
@@ -1,6 +1,7 @@
 mod common;
 use bdk_coin_select::{
-    float::Ordf32, BnbMetric, Candidate, CoinSelector, Drain, Target, TargetFee, TargetOutputs,
+    float::Ordf32, BnbMetric, BnbObserver, BnbOptions, BnbStats, BudgetLimit, Candidate,
+    CoinSelector, Drain, Exploration, NoBnbSolution, Target, TargetFee, TargetOutputs,
 };
 #[macro_use]
 extern crate alloc;
@@ -16,6 +17,7 @@ fn test_wv(mut rng: impl RngCore) -> impl Iterator<Item = Candidate> {
             weight: 100,
             input_count: rng.random_range(1..2),
             is_segwit: rng.random_bool(0.5),
+            ancestors: None,
         };
         // HACK: set is_segwit = true for all these tests because you can't actually lower bound
         // things easily with how segwit inputs interfere with their weights. We can't modify the
@@ -32,6 +34,8 @@ struct MinExcessThenWeight;
 const EXCESS_RATIO: f32 = 1_000_000_f32;
 
 impl BnbMetric for MinExcessThenWeight {
+    type Score = Ordf32;
+
     fn score(&mut self, cs: &CoinSelector<'_>, target: Target) -> Option<Ordf32> {
         let excess = cs.excess(target, Drain::NONE);
         if excess < 0 {
@@ -91,6 +95,9 @@ fn bnb_finds_an_exact_solution_in_n_iter() {
         // we're trying to find an exact selection value so set fees to 0
         fee: TargetFee::ZERO,
         max_weight: None,
+        min_input_count: None,
+        max_input_count: None,
+        truc: false,
     };
 
     let solutions = cs.bnb_solutions(target, MinExcessThenWeight);
@@ -126,6 +133,9 @@ fn bnb_finds_solution_if_possible_in_n_iter() {
         },
         fee: TargetFee::default(),
         max_weight: None,
+        min_input_count: None,
+        max_input_count: None,
+        truc: false,
     };
 
     let solutions = cs.bnb_solutions(target, MinExcessThenWeight);
@@ -143,7 +153,365 @@ fn bnb_finds_solution_if_possible_in_n_iter() {
     assert_eq!(excess, 0);
 }
 
+#[test]
+fn bnb_stats_count_the_search() {
+    let mut rng = TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+    let candidates = test_wv(&mut rng).take(18).collect::<Vec<_>>();
+    let cs = CoinSelector::new(&candidates);
+    let target = Target {
+        outputs: TargetOutputs {
+            value_sum: 8_314,
+            weight_sum: 0,
+            n_outputs: 1,
+        },
+        fee: TargetFee::default(),
+        max_weight: None,
+        min_input_count: None,
+        max_input_count: None,
+        truc: false,
+    };
+
+    let mut stats = BnbStats::default();
+    let solutions = cs
+        .bnb_solutions_with(target, MinExcessThenWeight, BnbOptions::new((), &mut stats))
+        .collect::<Vec<_>>();
+    let best_round = solutions.iter().rposition(|sol| sol.is_some()).unwrap() + 1;
+
+    assert_eq!(stats.rounds, solutions.len());
+    assert_eq!(stats.rounds, 164);
+    assert_eq!(stats.rounds_to_best, Some(best_round));
+    // every round took a branch off the queue, and the branches that were never expanded were
+    // dropped once the search finished
+    assert_eq!(stats.pushed, stats.rounds + stats.dropped);
+    assert!(stats.dropped > 0);
+    assert!(stats.peak_queue_len >= 1 && stats.peak_queue_len <= stats.pushed);
+    assert!(stats.pruned > 0);
+}
+
+/// Records the scores of the improvements it's told about.
+#[derive(Default)]
+struct Improvements(Vec<Ordf32>);
+
+impl BnbObserver for Improvements {
+    fn improved(&mut self, _cs: &CoinSelector<'_>, score: Ordf32) {
+        self.0.push(score);
+    }
+}
+
+#[test]
+fn run_bnb_with_observer_sees_every_improvement() {
+    let mut rng = TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+    let candidates = test_wv(&mut rng).take(18).collect::<Vec<_>>();
+    let target = Target {
+        outputs: TargetOutputs {
+            value_sum: 8_314,
+            weight_sum: 0,
+            n_outputs: 1,
+        },
+        fee: TargetFee::default(),
+        max_weight: None,
+        min_input_count: None,
+        max_input_count: None,
+        truc: false,
+    };
+
+    let mut improvements = Improvements::default();
+    let mut cs = CoinSelector::new(&candidates);
+    let score = cs
+        .run_bnb_with(
+            target,
+            MinExcessThenWeight,
+            BnbOptions::new(usize::MAX, &mut improvements),
+        )
+        .expect("found a solution")
+        .score;
+
+    assert_eq!(improvements.0.last(), Some(&score));
+    assert!(improvements.0.windows(2).all(|w| w[1] < w[0]));
+}
+
+fn target_8314() -> Target {
+    Target {
+        outputs: TargetOutputs {
+            value_sum: 8_314,
+            weight_sum: 0,
+            n_outputs: 1,
+        },
+        fee: TargetFee::default(),
+        max_weight: None,
+        min_input_count: None,
+        max_input_count: None,
+        truc: false,
+    }
+}
+
+#[test]
+fn depth_first_finds_the_best_solution_with_a_small_queue() {
+    let mut rng = TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+    let candidates = test_wv(&mut rng).take(18).collect::<Vec<_>>();
+    let target = target_8314();
+
+    let mut best_first = CoinSelector::new(&candidates);
+    let (best_first_score, _) = best_first
+        .run_bnb(target, MinExcessThenWeight, usize::MAX)
+        .expect("found a solution");
+
+    let mut stats = BnbStats::default();
+    let mut options = BnbOptions::new(usize::MAX, &mut stats);
+    options.exploration = Exploration::DepthFirst;
+    let mut depth_first = CoinSelector::new(&candidates);
+    let depth_first_score = depth_first
+        .run_bnb_with(target, MinExcessThenWeight, options)
+        .expect("found a solution")
+        .score;
+
+    assert_eq!(depth_first_score, best_first_score);
+    // the stack never holds more than one branch per candidate plus one
+    assert!(stats.peak_queue_len <= candidates.len() + 1);
+}
+
+#[test]
+fn max_queue_len_caps_the_queue() {
+    let mut rng = TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+    let candidates = test_wv(&mut rng).take(18).collect::<Vec<_>>();
+    let target = target_8314();
+
+    for exploration in [Exploration::BestFirst, Exploration::DepthFirst] {
+        let mut stats = BnbStats::default();
+        let mut options = BnbOptions::new(usize::MAX, &mut stats);
+        options.exploration = exploration;
+        options.max_queue_len = Some(4);
+        let mut cs = CoinSelector::new(&candidates);
+        let result = cs.run_bnb_with(target, MinExcessThenWeight, options);
+
+        assert!(result.is_ok(), "{:?}", exploration);
+        assert!(stats.peak_queue_len <= 4, "{:?}", exploration);
+        assert!(stats.dropped > 0, "{:?}", exploration);
+        // the search finished with an empty queue, and a branch is either expanded or dropped,
+        // never both
+        assert_eq!(
+            stats.pushed,
+            stats.rounds + stats.dropped,
+            "{:?}",
+            exploration
+        );
+    }
+}
+
+#[test]
+fn run_bnb_reports_dropped_branches() {
+    let mut rng = TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+    let candidates = test_wv(&mut rng).take(18).collect::<Vec<_>>();
+
+    // Not even the root fits in the queue.
+    let mut options = BnbOptions::new(usize::MAX, ());
+    options.max_queue_len = Some(0);
+    let mut cs = CoinSelector::new(&candidates);
+    match cs.run_bnb_with(target_8314(), MinExcessThenWeight, options) {
+        Err(NoBnbSolution::BudgetExhausted { limit, .. }) => {
+            assert_eq!(limit, BudgetLimit::QueueLen(0))
+        }
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn run_bnb_only_returns_selections_that_conflict() {
+    let mut rng = TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+    let candidates = test_wv(&mut rng).take(18).collect::<Vec<_>>();
+    let target = target_8314();
+
+    let mut unconstrained = CoinSelector::new(&candidates);
+    unconstrained
+        .run_bnb(target, MinExcessThenWeight, usize::MAX)
+        .expect("found a solution");
+    let conflict = (0..candidates.len())
+        .find(|&index| !unconstrained.is_selected(index))
+        .expect("some candidate isn't selected");
+
+    let mut cs = CoinSelector::new(&candidates);
+    cs.add_conflict(conflict);
+    cs.run_bnb(target, MinExcessThenWeight, usize::MAX)
+        .expect("found a solution");
+    assert!(cs.is_selected(conflict));
+
+    let mut cs = CoinSelector::new(&candidates);
+    cs.add_conflict(conflict);
+    cs.ban(conflict);
+    assert_eq!(
+        cs.run_bnb(target, MinExcessThenWeight, usize::MAX),
+        Err(NoBnbSolution::NoConflict)
+    );
+}
+
+#[test]
+fn bnb_session_carries_on_where_it_stopped() {
+    let mut rng = TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+    let candidates = test_wv(&mut rng).take(18).collect::<Vec<_>>();
+    let cs = CoinSelector::new(&candidates);
+    let target = target_8314();
+
+    let (best_score, _) = cs
+        .clone()
+        .run_bnb(target, MinExcessThenWeight, ())
+        .expect("found a solution");
+
+    let mut session = cs.bnb_session(target, MinExcessThenWeight, BnbOptions::new((), ()));
+    let mut runs = 0;
+    while !session.is_finished() {
+        // each run gets its own budget of 50 rounds
+        let rounds_before = session.progress().rounds;
+        let result = session.run(50);
+        assert!(session.progress().rounds - rounds_before <= 50);
+        if let Err(e) = result {
+            assert!(matches!(e, NoBnbSolution::BudgetExhausted { .. }));
+        }
+        runs += 1;
+    }
+
+    assert!(runs > 1);
+    let rounds = session.progress().rounds;
+    let solution = session.run(0).expect("found a solution");
+    assert_eq!(solution.score, best_score);
+    assert!(solution.proven_optimal);
+    assert_eq!(
+        session.progress().rounds,
+        rounds,
+        "a finished session does no more rounds"
+    );
+    assert!(
+        cs.selected_indices().is_empty(),
+        "leaves the selection as is"
+    );
+}
+
+#[test]
+fn bnb_session_reports_why_there_is_no_solution() {
+    let mut rng = TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+    let candidates = test_wv(&mut rng).take(18).collect::<Vec<_>>();
+    let cs = CoinSelector::new(&candidates);
+    let mut target = target_8314();
+    target.outputs.value_sum = 1_000_000;
+
+    let mut session = cs.bnb_session(target, MinExcessThenWeight, BnbOptions::new((), ()));
+    // the metric can tell straight away, so even no budget is enough
+    assert_eq!(
+        session.run(0).unwrap_err(),
+        NoBnbSolution::InsufficientFunds
+    );
+    assert!(session.is_finished());
+    assert!(session.best_solution().is_none());
+}
+
+/// Spends the least value, then the fewest inputs, compared exactly.
+struct MinValueThenInputs;
+
+impl BnbMetric for MinValueThenInputs {
+    type Score = (u64, usize);
+
+    fn score(&mut self, cs: &CoinSelector<'_>, target: Target) -> Option<(u64, usize)> {
+        if cs.excess(target, Drain::NONE) < 0 {
+            return None;
+        }
+        Some((cs.selected_value(), cs.input_count()))
+    }
+
+    fn bound(&mut self, cs: &CoinSelector<'_>, _target: Target) -> Option<(u64, usize)> {
+        // selecting more only adds value and inputs
+        Some((cs.selected_value(), cs.input_count()))
+    }
+
+    fn drain(&mut self, _cs: &CoinSelector<'_>, _target: Target) -> Drain {
+        Drain::NONE
+    }
+}
+
+#[test]
+fn run_bnb_compares_scores_of_any_ord_type() {
+    let candidate = |value, input_count| Candidate {
+        value,
+        weight: 100 * input_count as u64,
+        input_count,
+        is_segwit: true,
+        ancestors: None,
+    };
+    // an f32 can't tell these values apart
+    let candidates = [
+        candidate(50_000_004, 1),
+        candidate(50_000_001, 2),
+        candidate(50_000_001, 1),
+    ];
+    let target = Target {
+        outputs: TargetOutputs {
+            value_sum: 50_000_000,
+            weight_sum: 0,
+            n_outputs: 1,
+        },
+        fee: TargetFee::ZERO,
+        max_weight: None,
+        min_input_count: None,
+        max_input_count: None,
+        truc: false,
+    };
+
+    let mut cs = CoinSelector::new(&candidates);
+    let (score, drain) = cs
+        .run_bnb(target, MinValueThenInputs, usize::MAX)
+        .expect("finds a solution");
+    assert_eq!(score, (50_000_001, 1));
+    assert!(drain.is_none());
+    assert_eq!(cs.selected_indices().iter().collect::<Vec<_>>(), vec![2]);
+
+    let mut stats = BnbStats::default();
+    let solutions = CoinSelector::new(&candidates)
+        .run_bnb_top_k(
+            target,
+            MinValueThenInputs,
+            BnbOptions::new(usize::MAX, &mut stats),
+            2,
+        )
+        .expect("finds solutions");
+    let scores = solutions
+        .iter()
+        .map(|solution| solution.score)
+        .collect::<Vec<_>>();
+    assert_eq!(scores, vec![(50_000_001, 1), (50_000_001, 2)]);
+    assert!(stats.rounds > 0);
+}
+
 proptest! {
+    #[test]
+    #[cfg(not(debug_assertions))] // too slow if compiling for debug
+    fn depth_first_finds_the_same_score_as_best_first(num_inputs in 1usize..18, target_value in 0u64..10_000) {
+        let mut rng = TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+        let candidates = test_wv(&mut rng).take(num_inputs).collect::<Vec<_>>();
+        let target = Target {
+            outputs: TargetOutputs { value_sum: target_value, weight_sum: 0, n_outputs: 1 },
+            fee: TargetFee::ZERO,
+            max_weight: None,
+            min_input_count: None,
+            max_input_count: None,
+            truc: false,
+        };
+
+        let mut cs = CoinSelector::new(&candidates);
+        // the bound of `MinExcessThenWeight` only holds if sorted in descending value
+        cs.sort_candidates_by_key(|(_, wv)| core::cmp::Reverse(wv.value));
+
+        let best_first = cs
+            .clone()
+            .run_bnb(target, MinExcessThenWeight, usize::MAX)
+            .map(|(score, _)| score);
+        let mut options = BnbOptions::new(usize::MAX, ());
+        options.exploration = Exploration::DepthFirst;
+        let depth_first = cs
+            .clone()
+            .run_bnb_with(target, MinExcessThenWeight, options)
+            .map(|solution| solution.score);
+
+        prop_assert_eq!(depth_first, best_first);
+    }
+
     #[test]
     #[cfg(not(debug_assertions))] // too slow if compiling for debug
     fn bnb_always_finds_solution_if_possible(num_inputs in 1usize..18, target_value in 0u64..10_000) {
@@ -156,6 +524,9 @@ proptest! {
             outputs: TargetOutputs { value_sum: target_value, weight_sum: 0, n_outputs: 1 },
             fee: TargetFee::ZERO,
             max_weight: None,
+            min_input_count: None,
+            max_input_count: None,
+            truc: false,
         };
 
         let solutions = cs.bnb_solutions(target, MinExcessThenWeight);
@@ -203,6 +574,9 @@ proptest! {
             // we're trying to find an exact selection value so set fees to 0
             fee: TargetFee::ZERO,
             max_weight: None,
+            min_input_count: None,
+            max_input_count: None,
+            truc: false,
         };
 
         let solutions = cs.bnb_solutions(target, MinExcessThenWeight);
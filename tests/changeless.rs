@@ -16,6 +16,7 @@ fn test_wv(mut rng: impl RngCore) -> impl Iterator<Item = Candidate> {
             weight: rng.random_range(0..100),
             input_count: rng.random_range(1..2),
             is_segwit: false,
+            ancestors: None,
         }
     })
 }
@@ -67,13 +68,16 @@ proptest! {
                 ..TargetFee::ZERO
             },
             max_weight: None,
+            min_input_count: None,
+            max_input_count: None,
+            truc: false,
         };
 
         let make_metric = || {
             Changeless(LowestFee {
                 long_term_feerate: feerate,
                 dust_relay_feerate: FeeRate::from_sat_per_vb(1.0),
-                drain_weights,
+                drain_options: vec![drain_weights],
             })
         };
 
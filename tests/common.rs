@@ -1,8 +1,11 @@
 #![allow(dead_code)]
 
 use bdk_coin_select::{
-    float::Ordf32, metrics::LowestFee, BnbMetric, Candidate, CoinSelector, Drain, DrainWeights,
-    FeeRate, NoBnbSolution, Replace, Target, TargetFee, TargetOutputs,
+    float::Ordf32,
+    metrics::{Consolidate, ExactMatch, LowestFee, MinWeight, Waste},
+    Ancestors, BnbMetric, BnbProgress, BudgetLimit, Candidate, ChangePolicy, CoinSelector, Drain,
+    DrainWeights, FeeRate, NoBnbSolution, Replace, Target, TargetFee, TargetOutputs,
+    TX_FIXED_FIELD_WEIGHT,
 };
 use proptest::{
     prelude::*,
@@ -165,7 +168,7 @@ where
                 let has_change = metric.drain(&cs, target).is_some();
                 prop_assert!(
                     score >= lb_score,
-                    "checking branch: selection={} score={} change={} lb={}",
+                    "checking branch: selection={} score={:?} change={} lb={:?}",
                     cs,
                     score,
                     has_change,
@@ -184,8 +187,8 @@ where
                     prop_assert!(
                         descendant_score >= lb_score,
                         "
-                            parent={:8} change={} lb={} target_met={}
-                        descendant={:8} change={} score={}
+                            parent={:8} change={} lb={:?} target_met={}
+                        descendant={:8} change={} score={:?}
                         ",
                         cs,
                         parent_has_change,
@@ -232,6 +235,9 @@ impl StrategyParams {
                 n_outputs: self.n_target_outputs,
             },
             max_weight: self.max_weight,
+            min_input_count: None,
+            max_input_count: None,
+            truc: false,
         }
     }
 
@@ -257,10 +263,122 @@ impl StrategyParams {
 
     pub fn lowest_fee_metric(&self) -> LowestFee {
         LowestFee {
+            long_term_feerate: self.long_term_feerate(),
+            dust_relay_feerate: self.dust_relay_feerate(),
+            drain_options: vec![self.drain_weights()],
+        }
+    }
+
+    pub fn waste_metric(&self, excess_discount: f32) -> Waste {
+        Waste {
             long_term_feerate: self.long_term_feerate(),
             dust_relay_feerate: self.dust_relay_feerate(),
             drain_weights: self.drain_weights(),
+            excess_discount,
+        }
+    }
+
+    pub fn min_weight_metric(&self) -> MinWeight {
+        MinWeight {
+            dust_relay_feerate: self.dust_relay_feerate(),
+            drain_weights: self.drain_weights(),
+        }
+    }
+
+    pub fn exact_match_metric(&self) -> ExactMatch {
+        ExactMatch {
+            long_term_feerate: self.long_term_feerate(),
+            cost_of_change: ChangePolicy::min_value_and_waste(
+                self.drain_weights(),
+                self.drain_dust,
+                self.feerate(),
+                self.long_term_feerate(),
+            )
+            .min_value,
+        }
+    }
+
+    pub fn consolidate_metric(&self, fee_budget: Option<u64>) -> Consolidate {
+        Consolidate {
+            long_term_feerate: self.long_term_feerate(),
+            dust_relay_feerate: self.dust_relay_feerate(),
+            drain_weights: self.drain_weights(),
+            fee_budget,
+        }
+    }
+}
+
+/// A simple metric for combining with others in tests: minimizes the number of selected candidates.
+#[derive(Clone, Copy, Debug)]
+pub struct FewestInputs;
+
+impl BnbMetric for FewestInputs {
+    type Score = Ordf32;
+
+    fn score(&mut self, cs: &CoinSelector<'_>, target: Target) -> Option<Ordf32> {
+        if !cs.is_funded(target) {
+            return None;
+        }
+        Some(Ordf32(cs.selected().len() as f32))
+    }
+
+    fn bound(&mut self, cs: &CoinSelector<'_>, target: Target) -> Option<Ordf32> {
+        if !cs.is_fundable(target) {
+            return None;
         }
+        let missing_one = !cs.is_funded(target) as usize;
+        Some(Ordf32((cs.selected().len() + missing_one) as f32))
+    }
+
+    fn drain(&mut self, _cs: &CoinSelector<'_>, _target: Target) -> Drain {
+        Drain::NONE
+    }
+}
+
+/// A confirmed P2WPKH input worth `value`.
+pub fn p2wpkh_candidate(value: u64) -> Candidate {
+    p2wpkh_candidate_with_ancestors(value, None)
+}
+
+/// A P2WPKH input worth `value` that spends an output of the unconfirmed `ancestors`, if any.
+pub fn p2wpkh_candidate_with_ancestors(value: u64, ancestors: Option<Ancestors>) -> Candidate {
+    Candidate {
+        value,
+        weight: 272,
+        input_count: 1,
+        is_segwit: true,
+        ancestors,
+    }
+}
+
+/// A target that pays `value` to a single output at `feerate`, with no other constraints.
+pub fn single_output_target(value: u64, feerate: FeeRate) -> Target {
+    Target {
+        fee: TargetFee::from_feerate(feerate),
+        outputs: TargetOutputs {
+            value_sum: value,
+            weight_sum: 200 - TX_FIXED_FIELD_WEIGHT - 1,
+            n_outputs: 1,
+        },
+        max_weight: None,
+        min_input_count: None,
+        max_input_count: None,
+        truc: false,
+    }
+}
+
+/// Change to a taproot keyspend output of at least 1,000 sats.
+pub fn change_policy() -> ChangePolicy {
+    ChangePolicy::min_value(DrainWeights::TR_KEYSPEND, 1_000)
+}
+
+/// A tiny xorshift so the tests don't depend on a particular `rand` version.
+pub fn xorshift(mut state: u64) -> impl FnMut() -> u64 {
+    move || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state
     }
 }
 
@@ -277,12 +395,33 @@ pub fn gen_candidates(n: usize) -> Vec<Candidate> {
             weight,
             input_count,
             is_segwit,
+            ancestors: None,
         }
     })
     .take(n)
     .collect()
 }
 
+/// Same as [`gen_candidates`] except about half of the candidates spend unconfirmed outputs whose
+/// ancestors pay anywhere between 0 and 25 sats/vb.
+pub fn gen_candidates_with_ancestors(n: usize) -> Vec<Candidate> {
+    let mut rng = TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+    gen_candidates(n)
+        .into_iter()
+        .map(|mut candidate| {
+            if rng.random_bool(0.5) {
+                let weight = rng.random_range(400..4001);
+                candidate.ancestors = Some(Ancestors {
+                    fee: rng.random_range(0..weight * 25 / 4),
+                    weight,
+                    is_truc: false,
+                });
+            }
+            candidate
+        })
+        .collect()
+}
+
 pub fn print_candidates(params: &StrategyParams, cs: &CoinSelector<'_>) {
     println!("\tcandidates:");
     for (i, candidate) in cs.candidates() {
@@ -344,7 +483,7 @@ pub fn exhaustive_search<M>(
     cs: &mut CoinSelector,
     target: Target,
     metric: &mut M,
-) -> Option<(Ordf32, usize)>
+) -> Option<(M::Score, usize)>
 where
     M: BnbMetric,
 {
@@ -352,7 +491,7 @@ where
         cs.sort_candidates_by_descending_value_pwu();
     }
 
-    let mut best = Option::<(CoinSelector, Ordf32)>::None;
+    let mut best = Option::<(CoinSelector, M::Score)>::None;
     let mut rounds = 0;
 
     let iter = ExhaustiveIter::new(cs)?
@@ -374,7 +513,7 @@ where
     }
 
     if let Some((best_cs, score)) = &best {
-        println!("\t\tsolution={}, score={}", best_cs, score);
+        println!("\t\tsolution={}, score={:?}", best_cs, score);
         *cs = best_cs.clone();
     }
 
@@ -404,7 +543,7 @@ pub fn bnb_search<M>(
     target: Target,
     metric: M,
     max_rounds: usize,
-) -> Result<(Ordf32, usize), NoBnbSolution>
+) -> Result<(M::Score, usize), NoBnbSolution>
 where
     M: BnbMetric,
 {
@@ -415,15 +554,22 @@ where
         .take(max_rounds)
         .flatten()
         .last()
-        .ok_or(NoBnbSolution::RoundLimit { max_rounds, rounds })?;
-    println!("\t\tsolution={}, score={}", selection, score);
+        .ok_or(NoBnbSolution::BudgetExhausted {
+            limit: BudgetLimit::Rounds(max_rounds),
+            progress: BnbProgress {
+                rounds,
+                ..Default::default()
+            },
+        })?;
+    println!("\t\tsolution={}, score={:?}", selection, score);
     *cs = selection;
 
     Ok((score, rounds))
 }
 
-pub fn result_string<E>(res: &Result<(Ordf32, usize), E>, change: Drain) -> String
+pub fn result_string<S, E>(res: &Result<(S, usize), E>, change: Drain) -> String
 where
+    S: std::fmt::Debug,
     E: std::fmt::Debug,
 {
     match res {
@@ -433,7 +579,7 @@ where
             } else {
                 "None".to_string()
             };
-            format!("Ok(score={} rounds={} drain={})", score, rounds, drain)
+            format!("Ok(score={:?} rounds={} drain={})", score, rounds, drain)
         }
         err => format!("{:?}", err),
     }
@@ -535,15 +681,15 @@ pub fn compare_against_benchmarks<M: BnbMetric + Clone>(
 }
 
 #[allow(unused)]
-fn randomly_satisfy_target<'a, R: rand::Rng>(
+fn randomly_satisfy_target<'a, R: rand::Rng, M: BnbMetric>(
     cs: &CoinSelector<'a>,
     target: Target,
     rng: &mut R,
-    mut metric: impl BnbMetric,
+    mut metric: M,
 ) -> CoinSelector<'a> {
     let mut cs = cs.clone();
 
-    let mut last_score: Option<Ordf32> = None;
+    let mut last_score: Option<M::Score> = None;
     while let Some(next) = cs.unselected_indices().choose(rng) {
         cs.select(next);
         if cs.is_funded(target) {
@@ -0,0 +1,140 @@
+#![allow(unused_imports)]
+
+mod common;
+use bdk_coin_select::metrics::Consolidate;
+use bdk_coin_select::{
+    BnbMetric, Candidate, CoinSelector, Drain, DrainWeights, FeeRate, Target, TargetFee,
+    TargetOutputs, TX_FIXED_FIELD_WEIGHT,
+};
+use common::p2wpkh_candidate;
+use proptest::prelude::*;
+
+proptest! {
+    #![proptest_config(ProptestConfig {
+        ..Default::default()
+    })]
+
+    #[test]
+    #[cfg(not(debug_assertions))] // too slow if compiling for debug
+    fn can_eventually_find_best_solution(
+        n_candidates in 1..15_usize,        // candidates (n)
+        target_value in 500..500_000_u64,   // target value (sats)
+        n_target_outputs in 1usize..150,    // the number of outputs we're funding
+        target_weight in 0..10_000_u32,         // the sum of the weight of the outputs (wu)
+        replace in common::maybe_replace(0u64..10_000), // The weight of the transaction we're replacing
+        feerate in 1.0..100.0_f32,          // feerate (sats/vb)
+        feerate_lt_diff in -5.0..50.0_f32,  // longterm feerate diff (sats/vb)
+        drain_weight in 100..=500_u32,      // drain weight (wu)
+        drain_spend_weight in 1..=2000_u32, // drain spend weight (wu)
+        drain_dust in 100..=1000_u64,       // drain dust (sats)
+        n_drain_outputs in 1usize..150,     // the number of drain outputs
+        max_weight in common::maybe_max_weight(500u64..4_000), // optional max tx weight cap (wu)
+        fee_budget in proptest::option::of(100..50_000_u64), // optional fee budget (sats)
+    ) {
+        let params = common::StrategyParams { n_candidates, target_value, n_target_outputs, target_weight, replace, feerate, feerate_lt_diff, drain_weight, drain_spend_weight, drain_dust, n_drain_outputs, max_weight };
+        let candidates = common::gen_candidates(params.n_candidates);
+        let metric = params.consolidate_metric(fee_budget);
+        common::can_eventually_find_best_solution(params, candidates, metric)?;
+    }
+
+    #[test]
+    #[cfg(not(debug_assertions))] // too slow if compiling for debug
+    fn ensure_bound_is_not_too_tight(
+        n_candidates in 0..12_usize,        // candidates (n)
+        target_value in 500..500_000_u64,   // target value (sats)
+        n_target_outputs in 1usize..150,    // the number of outputs we're funding
+        target_weight in 0..10_000_u32,         // the sum of the weight of the outputs (wu)
+        replace in common::maybe_replace(0u64..10_000), // The weight of the transaction we're replacing
+        feerate in 1.0..100.0_f32,          // feerate (sats/vb)
+        feerate_lt_diff in -5.0..50.0_f32,  // longterm feerate diff (sats/vb)
+        drain_weight in 100..=500_u32,      // drain weight (wu)
+        drain_spend_weight in 1..=2000_u32, // drain spend weight (wu)
+        drain_dust in 100..=1000_u64,       // drain dust (sats)
+        n_drain_outputs in 1usize..150,     // the number of drain outputs
+        max_weight in common::maybe_max_weight(500u64..4_000), // optional max tx weight cap (wu)
+        fee_budget in proptest::option::of(100..50_000_u64), // optional fee budget (sats)
+    ) {
+        let params = common::StrategyParams { n_candidates, target_value, n_target_outputs, target_weight, replace, feerate, feerate_lt_diff, drain_weight, drain_spend_weight, drain_dust, n_drain_outputs, max_weight };
+        let candidates = common::gen_candidates(params.n_candidates);
+        let metric = params.consolidate_metric(fee_budget);
+        common::ensure_bound_is_not_too_tight(params, candidates, metric)?;
+    }
+
+    #[test]
+    #[cfg(not(debug_assertions))] // too slow if compiling for debug
+    fn ensure_bound_is_not_too_tight_with_ancestors(
+        n_candidates in 0..12_usize,        // candidates (n)
+        target_value in 500..500_000_u64,   // target value (sats)
+        n_target_outputs in 1usize..150,    // the number of outputs we're funding
+        target_weight in 0..10_000_u32,         // the sum of the weight of the outputs (wu)
+        replace in common::maybe_replace(0u64..10_000), // The weight of the transaction we're replacing
+        feerate in 1.0..100.0_f32,          // feerate (sats/vb)
+        feerate_lt_diff in -5.0..50.0_f32,  // longterm feerate diff (sats/vb)
+        drain_weight in 100..=500_u32,      // drain weight (wu)
+        drain_spend_weight in 1..=2000_u32, // drain spend weight (wu)
+        drain_dust in 100..=1000_u64,       // drain dust (sats)
+        n_drain_outputs in 1usize..150,     // the number of drain outputs
+        max_weight in common::maybe_max_weight(500u64..4_000), // optional max tx weight cap (wu)
+        fee_budget in proptest::option::of(100..50_000_u64), // optional fee budget (sats)
+    ) {
+        let params = common::StrategyParams { n_candidates, target_value, n_target_outputs, target_weight, replace, feerate, feerate_lt_diff, drain_weight, drain_spend_weight, drain_dust, n_drain_outputs, max_weight };
+        let candidates = common::gen_candidates_with_ancestors(params.n_candidates);
+        let metric = params.consolidate_metric(fee_budget);
+        common::ensure_bound_is_not_too_tight(params, candidates, metric)?;
+    }
+}
+
+fn consolidation_target(max_weight: Option<u64>) -> Target {
+    Target {
+        max_weight,
+        ..common::single_output_target(10_000, FeeRate::from_sat_per_vb(2.0))
+    }
+}
+
+fn consolidate_metric(fee_budget: Option<u64>) -> Consolidate {
+    Consolidate {
+        long_term_feerate: FeeRate::from_sat_per_vb(20.0),
+        dust_relay_feerate: FeeRate::from_sat_per_vb(3.0),
+        drain_weights: DrainWeights::TR_KEYSPEND,
+        fee_budget,
+    }
+}
+
+/// Below the long-term feerate every input is worth spending now.
+#[test]
+fn spends_every_input_below_long_term_feerate() {
+    let candidates = vec![p2wpkh_candidate(20_000); 10];
+    let target = consolidation_target(None);
+
+    let mut cs = CoinSelector::new(&candidates);
+    let (_, drain) = cs
+        .run_bnb(target, consolidate_metric(None), 100_000)
+        .expect("finds solution");
+    assert_eq!(cs.selected_indices().len(), candidates.len());
+    assert!(drain.is_some());
+}
+
+/// The consolidation is capped by `max_weight` and by the fee budget.
+#[test]
+fn respects_max_weight_and_fee_budget() {
+    let candidates = vec![p2wpkh_candidate(20_000); 10];
+
+    let target = consolidation_target(Some(2_000));
+    let mut cs = CoinSelector::new(&candidates);
+    let (_, drain) = cs
+        .run_bnb(target, consolidate_metric(None), 100_000)
+        .expect("finds solution");
+    assert!(cs.weight(target.outputs, drain.weights) <= 2_000);
+    // one more input would not fit
+    assert!(cs.weight(target.outputs, drain.weights) + 272 > 2_000);
+
+    let target = consolidation_target(None);
+    let fee_budget = 1_000;
+    let mut cs = CoinSelector::new(&candidates);
+    let (_, drain) = cs
+        .run_bnb(target, consolidate_metric(Some(fee_budget)), 100_000)
+        .expect("finds solution");
+    let fee = cs.fee(target.value(), drain.value);
+    assert!(fee <= fee_budget as i64);
+    assert!(cs.selected_indices().len() < candidates.len());
+}
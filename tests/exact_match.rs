@@ -0,0 +1,138 @@
+#![allow(unused_imports)]
+
+mod common;
+use bdk_coin_select::metrics::ExactMatch;
+use bdk_coin_select::{
+    BnbMetric, Candidate, ChangePolicy, CoinSelector, Drain, DrainWeights, FeeRate, Target,
+    TargetFee, TargetOutputs, TX_FIXED_FIELD_WEIGHT,
+};
+use common::p2wpkh_candidate;
+use proptest::prelude::*;
+
+proptest! {
+    #![proptest_config(ProptestConfig {
+        ..Default::default()
+    })]
+
+    #[test]
+    #[cfg(not(debug_assertions))] // too slow if compiling for debug
+    fn can_eventually_find_best_solution(
+        n_candidates in 1..15_usize,        // candidates (n)
+        target_value in 500..500_000_u64,   // target value (sats)
+        n_target_outputs in 1usize..150,    // the number of outputs we're funding
+        target_weight in 0..10_000_u32,         // the sum of the weight of the outputs (wu)
+        replace in common::maybe_replace(0u64..10_000), // The weight of the transaction we're replacing
+        feerate in 1.0..100.0_f32,          // feerate (sats/vb)
+        feerate_lt_diff in -5.0..50.0_f32,  // longterm feerate diff (sats/vb)
+        drain_weight in 100..=500_u32,      // drain weight (wu)
+        drain_spend_weight in 1..=2000_u32, // drain spend weight (wu)
+        drain_dust in 100..=1000_u64,       // drain dust (sats)
+        n_drain_outputs in 1usize..150,     // the number of drain outputs
+        max_weight in common::maybe_max_weight(500u64..4_000), // optional max tx weight cap (wu)
+    ) {
+        let params = common::StrategyParams { n_candidates, target_value, n_target_outputs, target_weight, replace, feerate, feerate_lt_diff, drain_weight, drain_spend_weight, drain_dust, n_drain_outputs, max_weight };
+        let candidates = common::gen_candidates(params.n_candidates);
+        let metric = params.exact_match_metric();
+        common::can_eventually_find_best_solution(params, candidates, metric)?;
+    }
+
+    #[test]
+    #[cfg(not(debug_assertions))] // too slow if compiling for debug
+    fn ensure_bound_is_not_too_tight(
+        n_candidates in 0..12_usize,        // candidates (n)
+        target_value in 500..500_000_u64,   // target value (sats)
+        n_target_outputs in 1usize..150,    // the number of outputs we're funding
+        target_weight in 0..10_000_u32,         // the sum of the weight of the outputs (wu)
+        replace in common::maybe_replace(0u64..10_000), // The weight of the transaction we're replacing
+        feerate in 1.0..100.0_f32,          // feerate (sats/vb)
+        feerate_lt_diff in -5.0..50.0_f32,  // longterm feerate diff (sats/vb)
+        drain_weight in 100..=500_u32,      // drain weight (wu)
+        drain_spend_weight in 1..=2000_u32, // drain spend weight (wu)
+        drain_dust in 100..=1000_u64,       // drain dust (sats)
+        n_drain_outputs in 1usize..150,     // the number of drain outputs
+        max_weight in common::maybe_max_weight(500u64..4_000), // optional max tx weight cap (wu)
+    ) {
+        let params = common::StrategyParams { n_candidates, target_value, n_target_outputs, target_weight, replace, feerate, feerate_lt_diff, drain_weight, drain_spend_weight, drain_dust, n_drain_outputs, max_weight };
+        let candidates = common::gen_candidates(params.n_candidates);
+        let metric = params.exact_match_metric();
+        common::ensure_bound_is_not_too_tight(params, candidates, metric)?;
+    }
+
+    #[test]
+    #[cfg(not(debug_assertions))] // too slow if compiling for debug
+    fn ensure_bound_is_not_too_tight_with_ancestors(
+        n_candidates in 0..12_usize,        // candidates (n)
+        target_value in 500..500_000_u64,   // target value (sats)
+        n_target_outputs in 1usize..150,    // the number of outputs we're funding
+        target_weight in 0..10_000_u32,         // the sum of the weight of the outputs (wu)
+        replace in common::maybe_replace(0u64..10_000), // The weight of the transaction we're replacing
+        feerate in 1.0..100.0_f32,          // feerate (sats/vb)
+        feerate_lt_diff in -5.0..50.0_f32,  // longterm feerate diff (sats/vb)
+        drain_weight in 100..=500_u32,      // drain weight (wu)
+        drain_spend_weight in 1..=2000_u32, // drain spend weight (wu)
+        drain_dust in 100..=1000_u64,       // drain dust (sats)
+        n_drain_outputs in 1usize..150,     // the number of drain outputs
+        max_weight in common::maybe_max_weight(500u64..4_000), // optional max tx weight cap (wu)
+    ) {
+        let params = common::StrategyParams { n_candidates, target_value, n_target_outputs, target_weight, replace, feerate, feerate_lt_diff, drain_weight, drain_spend_weight, drain_dust, n_drain_outputs, max_weight };
+        let candidates = common::gen_candidates_with_ancestors(params.n_candidates);
+        let metric = params.exact_match_metric();
+        common::ensure_bound_is_not_too_tight(params, candidates, metric)?;
+    }
+}
+
+fn exact_match_setup() -> (Target, ExactMatch) {
+    let feerate = FeeRate::from_sat_per_vb(10.0);
+    let long_term_feerate = FeeRate::from_sat_per_vb(5.0);
+    let target = common::single_output_target(100_000, feerate);
+    let metric = ExactMatch {
+        long_term_feerate,
+        cost_of_change: ChangePolicy::min_value_and_waste(
+            DrainWeights::TR_KEYSPEND,
+            330,
+            feerate,
+            long_term_feerate,
+        )
+        .min_value,
+    };
+    (target, metric)
+}
+
+/// Only the selection whose excess lands in the cost-of-change window is a solution, even though
+/// the single large candidate is cheaper to spend.
+#[test]
+fn finds_selection_within_window() {
+    let (target, metric) = exact_match_setup();
+
+    // make the last two candidates together overshoot the target by just 100 sats
+    let mut candidates = vec![
+        p2wpkh_candidate(150_000),
+        p2wpkh_candidate(60_000),
+        p2wpkh_candidate(45_000),
+    ];
+    let mut cs = CoinSelector::new(&candidates);
+    cs.select(1);
+    cs.select(2);
+    let excess = cs.excess(target, Drain::NONE);
+    candidates[2].value -= (excess - 100) as u64;
+
+    let mut cs = CoinSelector::new(&candidates);
+    cs.run_bnb(target, metric, 10_000).expect("finds solution");
+    assert_eq!(cs.selected_indices().iter().collect::<Vec<_>>(), vec![1, 2]);
+    assert_eq!(cs.excess(target, Drain::NONE), 100);
+}
+
+/// Once the excess of a branch is above the window no descendant can get back into it.
+#[test]
+fn prunes_branch_once_excess_is_above_window() {
+    let (target, mut metric) = exact_match_setup();
+    let candidates = vec![p2wpkh_candidate(150_000), p2wpkh_candidate(60_000)];
+
+    let mut cs = CoinSelector::new(&candidates);
+    assert!(metric.bound(&cs, target).is_some());
+    cs.select(0);
+    assert!(metric.bound(&cs, target).is_none());
+    assert!(CoinSelector::new(&candidates)
+        .run_bnb(target, metric, 10_000)
+        .is_err());
+}
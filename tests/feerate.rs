@@ -0,0 +1,107 @@
+use bdk_coin_select::{Candidate, CoinSelector, DrainWeights, FeeRate};
+
+#[test]
+fn run_bitcoin_core_feerate_tests() {
+    // see GetFeeTest in amount_tests.cpp
+    //
+    // https://github.com/bitcoin/bitcoin/blob/e69796c79c0aa202087a13ba62d9fbcc1c8754d4/src/test/amount_tests.cpp
+    let fee = |sat_per_kvb: u64, vbytes: u64| {
+        FeeRate::from_sat_per_kvb(sat_per_kvb).implied_fee(vbytes * 4)
+    };
+
+    assert_eq!(fee(0, 0), 0);
+    assert_eq!(fee(0, 100_000), 0);
+
+    assert_eq!(fee(1_000, 0), 0);
+    assert_eq!(fee(1_000, 1), 1);
+    assert_eq!(fee(1_000, 121), 121);
+    assert_eq!(fee(1_000, 999), 999);
+    assert_eq!(fee(1_000, 1_000), 1_000);
+    assert_eq!(fee(1_000, 9_000), 9_000);
+
+    // fees are rounded up
+    assert_eq!(fee(123, 0), 0);
+    assert_eq!(fee(123, 8), 1);
+    assert_eq!(fee(123, 9), 2);
+    assert_eq!(fee(123, 121), 15);
+    assert_eq!(fee(123, 999), 123);
+    assert_eq!(fee(123, 1_000), 123);
+    assert_eq!(fee(123, 9_000), 1_107);
+
+    // the weight is rounded up to vbytes first
+    assert_eq!(FeeRate::from_sat_per_kvb(1_000).implied_fee(5), 2);
+    assert_eq!(FeeRate::from_sat_per_kvb(1_000).implied_fee_wu(5), 2);
+    assert_eq!(FeeRate::from_sat_per_kvb(1_000).implied_fee_wu(4), 1);
+}
+
+#[test]
+fn float_conversions_round_to_sat_per_kvb() {
+    assert_eq!(FeeRate::from_sat_per_vb(1.0), FeeRate::DEFAULT_MIN_RELAY);
+    assert_eq!(FeeRate::from_sat_per_vb(2.5).as_sat_per_kvb(), 2_500);
+    assert_eq!(FeeRate::from_sat_per_vb(0.0012).as_sat_per_kvb(), 1);
+    assert_eq!(FeeRate::from_sat_per_wu(0.25), FeeRate::DEFAULT_MIN_RELAY);
+    assert_eq!(FeeRate::from_btc_per_kvb(0.0001).as_sat_per_kvb(), 10_000);
+    assert_eq!(FeeRate::from_sat_per_kvb(2_500).as_sat_vb(), 2.5);
+    assert_eq!(FeeRate::from_sat_per_kvb(1_000).spwu(), 0.25);
+}
+
+#[test]
+fn feerate_from_fee_and_size_rounds_down() {
+    assert_eq!(FeeRate::from_vb(1_000, 1_000).as_sat_per_kvb(), 1_000);
+    assert_eq!(FeeRate::from_vb(1_000, 3).as_sat_per_kvb(), 333_333);
+    assert_eq!(FeeRate::from_wu(1_000, 3).as_sat_per_kvb(), 1_333_333);
+    // never claims more than the fee pays for
+    let feerate = FeeRate::from_wu(12_345, 6_789);
+    assert!(feerate.implied_fee_wu(6_789) <= 12_345);
+}
+
+#[test]
+fn fees_of_large_values_are_exact() {
+    // f32 can't represent every value this large
+    let value = 2_100_000_000_000_001;
+    let feerate = FeeRate::from_sat_per_kvb(1_234);
+    let candidate = Candidate {
+        value,
+        weight: 272,
+        input_count: 1,
+        is_segwit: true,
+        ancestors: None,
+    };
+    // 272 * 1234 / 4000 = 83.912
+    assert_eq!(candidate.implied_fee_sats(feerate), 84);
+    assert_eq!(candidate.effective_value_sats(feerate), value as i64 - 84);
+
+    let candidates = [candidate];
+    let mut cs = CoinSelector::new(&candidates);
+    cs.select(0);
+    let input_fee = feerate.implied_fee_wu(cs.input_weight());
+    assert_eq!(cs.effective_value(feerate), value as i64 - input_fee as i64);
+
+    let drain_weights = DrainWeights::TR_KEYSPEND;
+    assert_eq!(
+        drain_weights.spend_fee(feerate),
+        (drain_weights.spend_weight * 1_234 + 3_999) / 4_000
+    );
+    // one more output doesn't grow the output count varint
+    assert_eq!(
+        drain_weights.waste_sats(feerate, feerate, 1),
+        feerate.implied_fee_wu(drain_weights.output_weight) + drain_weights.spend_fee(feerate)
+    );
+}
+
+#[test]
+fn sorting_by_value_pwu_is_exact() {
+    let candidate = |value, weight| Candidate {
+        value,
+        weight,
+        input_count: 1,
+        is_segwit: true,
+        ancestors: None,
+    };
+    // f32 rounds both values per weight unit to 2^24, which would put the second one first
+    let candidates = [candidate(16_777_217, 1), candidate(33_554_432, 2)];
+    let mut cs = CoinSelector::new(&candidates);
+    cs.sort_candidates_by_descending_value_pwu();
+    let order = cs.candidates().map(|(index, _)| index).collect::<Vec<_>>();
+    assert_eq!(order, vec![0, 1]);
+}
@@ -0,0 +1,95 @@
+mod common;
+use bdk_coin_select::{CoinSelector, Drain, FeeRate, SelectError, Target};
+use common::{change_policy, p2wpkh_candidate, xorshift};
+use proptest::prelude::*;
+
+fn target(value: u64) -> Target {
+    common::single_output_target(value, FeeRate::from_sat_per_vb(5.0))
+}
+
+proptest! {
+    #[test]
+    #[cfg(not(debug_assertions))] // too slow if compiling for debug
+    fn selection_always_meets_target(
+        values in proptest::collection::vec(1_000..200_000_u64, 1..30),
+        target_value in 1_000..1_000_000_u64,
+        seed in 1..u64::MAX,
+    ) {
+        let candidates = values.into_iter().map(p2wpkh_candidate).collect::<Vec<_>>();
+        let target = target(target_value);
+        let mut cs = CoinSelector::new(&candidates);
+        match cs.select_knapsack(target, change_policy(), &mut xorshift(seed)) {
+            Ok(drain) => {
+                prop_assert!(cs.is_funded_with_drain(target, drain));
+                prop_assert_eq!(drain, cs.drain(target, change_policy()));
+            }
+            Err(SelectError::InsufficientFunds(_)) => {
+                prop_assert!(!cs.is_fundable(target));
+            }
+            Err(err) => prop_assert!(false, "unexpected error: {}", err),
+        }
+    }
+}
+
+#[test]
+fn finds_exact_match() {
+    let mut candidates = vec![
+        p2wpkh_candidate(100_000),
+        p2wpkh_candidate(31_000),
+        p2wpkh_candidate(47_000),
+        p2wpkh_candidate(60_000),
+    ];
+    // make the second and third candidates together meet the target exactly
+    let target = target(75_000);
+    let mut cs = CoinSelector::new(&candidates);
+    cs.select(1);
+    cs.select(2);
+    candidates[2].value -= cs.excess(target, Drain::NONE) as u64;
+
+    let mut cs = CoinSelector::new(&candidates);
+    let drain = cs
+        .select_knapsack(target, change_policy(), &mut xorshift(42))
+        .expect("finds selection");
+    assert_eq!(cs.selected_indices().iter().collect::<Vec<_>>(), vec![1, 2]);
+    assert_eq!(cs.excess(target, Drain::NONE), 0);
+    assert!(drain.is_none());
+}
+
+#[test]
+fn prefers_single_larger_candidate_when_closer() {
+    let candidates = vec![
+        p2wpkh_candidate(10_000),
+        p2wpkh_candidate(10_000),
+        p2wpkh_candidate(60_000),
+    ];
+    let target = target(25_000);
+    let mut cs = CoinSelector::new(&candidates);
+    let drain = cs
+        .select_knapsack(target, change_policy(), &mut xorshift(42))
+        .expect("finds selection");
+    assert_eq!(cs.selected_indices().iter().collect::<Vec<_>>(), vec![2]);
+    assert!(drain.is_some());
+}
+
+#[test]
+fn errors() {
+    let candidates = vec![p2wpkh_candidate(10_000), p2wpkh_candidate(20_000)];
+    let mut cs = CoinSelector::new(&candidates);
+    assert!(matches!(
+        cs.select_knapsack(target(100_000), change_policy(), &mut xorshift(1)),
+        Err(SelectError::InsufficientFunds(_))
+    ));
+
+    let target = Target {
+        max_weight: Some(500),
+        min_input_count: None,
+        max_input_count: None,
+        truc: false,
+        ..target(25_000)
+    };
+    assert_eq!(
+        cs.select_knapsack(target, change_policy(), &mut xorshift(1)),
+        Err(SelectError::MaxWeightExceeded)
+    );
+    assert!(cs.is_empty());
+}
@@ -0,0 +1,247 @@
+#![allow(unused_imports)]
+
+mod common;
+use bdk_coin_select::metrics::{Lexicographic, LowestFee, Quantized};
+use bdk_coin_select::{
+    float::Ordf32, BnbMetric, Candidate, CoinSelector, Drain, DrainWeights, FeeRate, Target,
+    TargetFee, TargetOutputs, TX_FIXED_FIELD_WEIGHT,
+};
+use common::{p2wpkh_candidate, FewestInputs};
+use proptest::prelude::*;
+
+proptest! {
+    #![proptest_config(ProptestConfig {
+        ..Default::default()
+    })]
+
+    #[test]
+    #[cfg(not(debug_assertions))] // too slow if compiling for debug
+    fn can_eventually_find_best_solution(
+        n_candidates in 1..15_usize,        // candidates (n)
+        target_value in 500..500_000_u64,   // target value (sats)
+        n_target_outputs in 1usize..150,    // the number of outputs we're funding
+        target_weight in 0..10_000_u32,         // the sum of the weight of the outputs (wu)
+        replace in common::maybe_replace(0u64..10_000), // The weight of the transaction we're replacing
+        feerate in 1.0..100.0_f32,          // feerate (sats/vb)
+        feerate_lt_diff in -5.0..50.0_f32,  // longterm feerate diff (sats/vb)
+        drain_weight in 100..=500_u32,      // drain weight (wu)
+        drain_spend_weight in 1..=2000_u32, // drain spend weight (wu)
+        drain_dust in 100..=1000_u64,       // drain dust (sats)
+        n_drain_outputs in 1usize..150,     // the number of drain outputs
+        max_weight in common::maybe_max_weight(500u64..4_000), // optional max tx weight cap (wu)
+        tolerance in 1.0..10_000.0_f32,     // how close fees must be to count as equal (sats)
+    ) {
+        let params = common::StrategyParams { n_candidates, target_value, n_target_outputs, target_weight, replace, feerate, feerate_lt_diff, drain_weight, drain_spend_weight, drain_dust, n_drain_outputs, max_weight };
+        let candidates = common::gen_candidates(params.n_candidates);
+        let metric = Lexicographic { primary: Quantized { metric: params.lowest_fee_metric(), tolerance }, secondary: FewestInputs };
+        common::can_eventually_find_best_solution(params, candidates, metric)?;
+    }
+
+    #[test]
+    #[cfg(not(debug_assertions))] // too slow if compiling for debug
+    fn ensure_bound_is_not_too_tight(
+        n_candidates in 0..12_usize,        // candidates (n)
+        target_value in 500..500_000_u64,   // target value (sats)
+        n_target_outputs in 1usize..150,    // the number of outputs we're funding
+        target_weight in 0..10_000_u32,         // the sum of the weight of the outputs (wu)
+        replace in common::maybe_replace(0u64..10_000), // The weight of the transaction we're replacing
+        feerate in 1.0..100.0_f32,          // feerate (sats/vb)
+        feerate_lt_diff in -5.0..50.0_f32,  // longterm feerate diff (sats/vb)
+        drain_weight in 100..=500_u32,      // drain weight (wu)
+        drain_spend_weight in 1..=2000_u32, // drain spend weight (wu)
+        drain_dust in 100..=1000_u64,       // drain dust (sats)
+        n_drain_outputs in 1usize..150,     // the number of drain outputs
+        max_weight in common::maybe_max_weight(500u64..4_000), // optional max tx weight cap (wu)
+        tolerance in 1.0..10_000.0_f32,     // how close fees must be to count as equal (sats)
+    ) {
+        let params = common::StrategyParams { n_candidates, target_value, n_target_outputs, target_weight, replace, feerate, feerate_lt_diff, drain_weight, drain_spend_weight, drain_dust, n_drain_outputs, max_weight };
+        let candidates = common::gen_candidates(params.n_candidates);
+        let metric = Lexicographic { primary: Quantized { metric: params.lowest_fee_metric(), tolerance }, secondary: FewestInputs };
+        common::ensure_bound_is_not_too_tight(params, candidates, metric)?;
+    }
+}
+
+fn setup() -> (Vec<Candidate>, Target, LowestFee) {
+    let candidates = vec![
+        p2wpkh_candidate(60_000),
+        p2wpkh_candidate(60_000),
+        // a single input that is much heavier than the two above together
+        Candidate {
+            value: 200_000,
+            weight: 1_000,
+            input_count: 1,
+            is_segwit: false,
+            ancestors: None,
+        },
+    ];
+    let target = common::single_output_target(100_000, FeeRate::from_sat_per_vb(10.0));
+    let lowest_fee = LowestFee {
+        long_term_feerate: FeeRate::from_sat_per_vb(10.0),
+        dust_relay_feerate: FeeRate::from_sat_per_vb(3.0),
+        drain_options: vec![DrainWeights::TR_KEYSPEND],
+    };
+    (candidates, target, lowest_fee)
+}
+
+/// With a small tolerance the primary metric decides: the two light inputs are cheaper to spend.
+#[test]
+fn primary_metric_takes_precedence() {
+    let (candidates, target, lowest_fee) = setup();
+
+    let mut expected = CoinSelector::new(&candidates);
+    let (_, expected_drain) = expected
+        .run_bnb(target, lowest_fee.clone(), 10_000)
+        .expect("finds solution");
+
+    let metric = Lexicographic {
+        primary: Quantized {
+            metric: lowest_fee,
+            tolerance: 1.0,
+        },
+        secondary: FewestInputs,
+    };
+    let mut cs = CoinSelector::new(&candidates);
+    let (_, drain) = cs.run_bnb(target, metric, 10_000).expect("finds solution");
+    assert_eq!(cs.selected_indices(), expected.selected_indices());
+    assert_eq!(drain, expected_drain);
+    assert_eq!(cs.selected_indices().iter().collect::<Vec<_>>(), vec![0, 1]);
+}
+
+/// With a tolerance larger than any fee difference every selection ties on the primary metric, so
+/// the secondary metric picks the selection with the fewest inputs.
+#[test]
+fn secondary_metric_breaks_ties() {
+    let (candidates, target, lowest_fee) = setup();
+    let metric = Lexicographic {
+        primary: Quantized {
+            metric: lowest_fee,
+            tolerance: 1_000_000.0,
+        },
+        secondary: FewestInputs,
+    };
+    let mut cs = CoinSelector::new(&candidates);
+    cs.run_bnb(target, metric, 10_000).expect("finds solution");
+    assert_eq!(cs.selected_indices().iter().collect::<Vec<_>>(), vec![2]);
+}
+
+/// Scores every funded selection the same, like a primary metric whose scores are all tied.
+#[derive(Clone, Copy)]
+struct Constant(f32);
+
+impl BnbMetric for Constant {
+    type Score = Ordf32;
+
+    fn score(&mut self, cs: &CoinSelector<'_>, target: Target) -> Option<Ordf32> {
+        cs.is_funded(target).then(|| Ordf32(self.0))
+    }
+
+    fn bound(&mut self, cs: &CoinSelector<'_>, target: Target) -> Option<Ordf32> {
+        cs.is_fundable(target).then(|| Ordf32(self.0))
+    }
+
+    fn drain(&mut self, _cs: &CoinSelector<'_>, _target: Target) -> Drain {
+        Drain::NONE
+    }
+}
+
+/// However large the tied primary scores are, the secondary scores still tell selections apart.
+#[test]
+fn secondary_metric_breaks_ties_of_large_primary_scores() {
+    let (candidates, target, _) = setup();
+    let candidates = candidates
+        .iter()
+        .chain(&candidates)
+        .copied()
+        .collect::<Vec<_>>();
+    let mut metric = Lexicographic {
+        primary: Constant(2_000_000.0),
+        secondary: FewestInputs,
+    };
+    let mut three_inputs = CoinSelector::new(&candidates);
+    let mut four_inputs = CoinSelector::new(&candidates);
+    for index in 0..4 {
+        if index < 3 {
+            three_inputs.select(index);
+        }
+        four_inputs.select(index);
+    }
+    assert!(
+        metric.score(&three_inputs, target).expect("funded")
+            < metric.score(&four_inputs, target).expect("funded")
+    );
+}
+
+/// Spends the least value, compared exactly.
+#[derive(Clone, Copy)]
+struct MinValue;
+
+impl BnbMetric for MinValue {
+    type Score = u64;
+
+    fn score(&mut self, cs: &CoinSelector<'_>, target: Target) -> Option<u64> {
+        cs.is_funded(target).then(|| cs.selected_value())
+    }
+
+    fn bound(&mut self, cs: &CoinSelector<'_>, target: Target) -> Option<u64> {
+        // selecting more only adds value
+        cs.is_fundable(target).then(|| cs.selected_value())
+    }
+
+    fn drain(&mut self, _cs: &CoinSelector<'_>, _target: Target) -> Drain {
+        Drain::NONE
+    }
+}
+
+/// Spends the fewest inputs, counted exactly.
+#[derive(Clone, Copy)]
+struct MinInputs;
+
+impl BnbMetric for MinInputs {
+    type Score = usize;
+
+    fn score(&mut self, cs: &CoinSelector<'_>, _target: Target) -> Option<usize> {
+        Some(cs.input_count())
+    }
+
+    fn bound(&mut self, cs: &CoinSelector<'_>, _target: Target) -> Option<usize> {
+        // selecting more only adds inputs
+        Some(cs.input_count())
+    }
+
+    fn drain(&mut self, _cs: &CoinSelector<'_>, _target: Target) -> Drain {
+        Drain::NONE
+    }
+}
+
+/// The inner metrics can score with any type, not just `Ordf32`.
+#[test]
+fn combines_metrics_with_exact_scores() {
+    let candidate = |value, input_count| Candidate {
+        value,
+        weight: 100 * input_count as u64,
+        input_count,
+        is_segwit: true,
+        ancestors: None,
+    };
+    // an f32 can't tell these values apart
+    let candidates = [
+        candidate(50_000_004, 1),
+        candidate(50_000_001, 2),
+        candidate(50_000_001, 1),
+    ];
+    let target = common::single_output_target(50_000_000, FeeRate::ZERO);
+
+    let mut cs = CoinSelector::new(&candidates);
+    let (score, _) = cs
+        .run_bnb(
+            target,
+            Lexicographic {
+                primary: MinValue,
+                secondary: MinInputs,
+            },
+            usize::MAX,
+        )
+        .expect("finds solution");
+    assert_eq!(score, (50_000_001, 1));
+    assert_eq!(cs.selected_indices().iter().collect::<Vec<_>>(), vec![2]);
+}
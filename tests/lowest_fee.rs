@@ -1,11 +1,14 @@
 #![allow(unused_imports)]
 
 mod common;
-use bdk_coin_select::metrics::{Changeless, LowestFee};
+use bdk_coin_select::metrics::{self, Changeless, LowestFee};
 use bdk_coin_select::{
-    BnbMetric, Candidate, ChangePolicy, CoinSelector, Drain, DrainWeights, FeeRate, NoBnbSolution,
-    Replace, Target, TargetFee, TargetOutputs, TX_FIXED_FIELD_WEIGHT,
+    float::Ordf32, Ancestors, BnbBudget, BnbMetric, BnbOptions, BnbProgress, BnbStats, BudgetLimit,
+    Candidate, ChangePolicy, CoinSelector, Drain, DrainWeights, Exploration, FeeRate, MaxNodes,
+    NoBnbSolution, Replace, SelectError, StopWhen, Target, TargetFee, TargetOutputs,
+    TX_FIXED_FIELD_WEIGHT,
 };
+use core::sync::atomic::{AtomicBool, Ordering};
 use proptest::prelude::*;
 
 proptest! {
@@ -35,6 +38,63 @@ proptest! {
         common::can_eventually_find_best_solution(params, candidates, metric)?;
     }
 
+    #[test]
+    #[cfg(not(debug_assertions))] // too slow if compiling for debug
+    fn can_eventually_find_best_solution_with_ancestors(
+        n_candidates in 1..20_usize,        // candidates (n)
+        target_value in 500..500_000_u64,   // target value (sats)
+        n_target_outputs in 1usize..150,    // the number of outputs we're funding
+        target_weight in 0..10_000_u32,         // the sum of the weight of the outputs (wu)
+        replace in common::maybe_replace(0u64..10_000), // The weight of the transaction we're replacing
+        feerate in 1.0..100.0_f32,          // feerate (sats/vb)
+        feerate_lt_diff in -5.0..50.0_f32,  // longterm feerate diff (sats/vb)
+        drain_weight in 100..=500_u32,      // drain weight (wu)
+        drain_spend_weight in 1..=2000_u32, // drain spend weight (wu)
+        drain_dust in 100..=1000_u64,       // drain dust (sats)
+        n_drain_outputs in 1usize..150,     // the number of drain outputs
+        max_weight in common::maybe_max_weight(500u64..4_000), // optional max tx weight cap (wu)
+    ) {
+        let params = common::StrategyParams { n_candidates, target_value, n_target_outputs, target_weight, replace, feerate, feerate_lt_diff, drain_weight, drain_spend_weight, drain_dust, n_drain_outputs , max_weight };
+        let candidates = common::gen_candidates_with_ancestors(params.n_candidates);
+        let metric = params.lowest_fee_metric();
+        common::can_eventually_find_best_solution(params, candidates, metric)?;
+    }
+
+    #[test]
+    #[cfg(not(debug_assertions))] // too slow if compiling for debug
+    fn can_eventually_find_best_solution_with_conflicts(
+        n_candidates in 1..15_usize,        // candidates (n)
+        target_value in 500..500_000_u64,   // target value (sats)
+        n_target_outputs in 1usize..150,    // the number of outputs we're funding
+        target_weight in 0..10_000_u32,         // the sum of the weight of the outputs (wu)
+        replace in common::maybe_replace(0u64..10_000), // The weight of the transaction we're replacing
+        feerate in 1.0..100.0_f32,          // feerate (sats/vb)
+        feerate_lt_diff in -5.0..50.0_f32,  // longterm feerate diff (sats/vb)
+        drain_weight in 100..=500_u32,      // drain weight (wu)
+        drain_spend_weight in 1..=2000_u32, // drain spend weight (wu)
+        drain_dust in 100..=1000_u64,       // drain dust (sats)
+        n_drain_outputs in 1usize..150,     // the number of drain outputs
+        max_weight in common::maybe_max_weight(500u64..4_000), // optional max tx weight cap (wu)
+        conflicts in proptest::collection::vec(0..15_usize, 1..3), // the inputs of the tx we're replacing
+    ) {
+        let params = common::StrategyParams { n_candidates, target_value, n_target_outputs, target_weight, replace, feerate, feerate_lt_diff, drain_weight, drain_spend_weight, drain_dust, n_drain_outputs , max_weight };
+        let candidates = common::gen_candidates(params.n_candidates);
+        let target = params.target();
+        let mut cs = CoinSelector::new(&candidates);
+        for conflict in conflicts {
+            cs.add_conflict(conflict % n_candidates);
+        }
+
+        let expected = common::exhaustive_search(&mut cs.clone(), target, &mut params.lowest_fee_metric())
+            .map(|(score, _)| score);
+        let mut selection = cs.clone();
+        let result = selection.run_bnb(target, params.lowest_fee_metric(), usize::MAX);
+        prop_assert_eq!(result.as_ref().ok().map(|&(score, _)| score), expected);
+        if result.is_ok() {
+            prop_assert!(selection.is_conflicting());
+        }
+    }
+
     #[test]
     #[cfg(not(debug_assertions))] // too slow if compiling for debug
     fn ensure_bound_is_not_too_tight(
@@ -57,6 +117,78 @@ proptest! {
         common::ensure_bound_is_not_too_tight(params, candidates, metric)?;
     }
 
+    #[test]
+    #[cfg(not(debug_assertions))] // too slow if compiling for debug
+    fn ensure_bound_is_not_too_tight_with_ancestors(
+        n_candidates in 0..15_usize,        // candidates (n)
+        target_value in 500..500_000_u64,   // target value (sats)
+        n_target_outputs in 1usize..150,    // the number of outputs we're funding
+        target_weight in 0..10_000_u32,         // the sum of the weight of the outputs (wu)
+        replace in common::maybe_replace(0u64..10_000), // The weight of the transaction we're replacing
+        feerate in 1.0..100.0_f32,          // feerate (sats/vb)
+        feerate_lt_diff in -5.0..50.0_f32,  // longterm feerate diff (sats/vb)
+        drain_weight in 100..=500_u32,      // drain weight (wu)
+        drain_spend_weight in 1..=2000_u32, // drain spend weight (wu)
+        drain_dust in 100..=1000_u64,       // drain dust (sats)
+        n_drain_outputs in 1usize..150,     // the number of drain outputs
+        max_weight in common::maybe_max_weight(500u64..4_000), // optional max tx weight cap (wu)
+    ) {
+        let params = common::StrategyParams { n_candidates, target_value, n_target_outputs, target_weight, replace, feerate, feerate_lt_diff, drain_weight, drain_spend_weight, drain_dust, n_drain_outputs , max_weight };
+        let candidates = common::gen_candidates_with_ancestors(params.n_candidates);
+        let metric = params.lowest_fee_metric();
+        common::ensure_bound_is_not_too_tight(params, candidates, metric)?;
+    }
+
+    #[test]
+    #[cfg(not(debug_assertions))] // too slow if compiling for debug
+    fn can_eventually_find_best_solution_with_drain_options(
+        n_candidates in 1..15_usize,        // candidates (n)
+        target_value in 500..500_000_u64,   // target value (sats)
+        n_target_outputs in 1usize..150,    // the number of outputs we're funding
+        target_weight in 0..10_000_u32,         // the sum of the weight of the outputs (wu)
+        replace in common::maybe_replace(0u64..10_000), // The weight of the transaction we're replacing
+        feerate in 1.0..100.0_f32,          // feerate (sats/vb)
+        feerate_lt_diff in -5.0..50.0_f32,  // longterm feerate diff (sats/vb)
+        drain_weight in 100..=500_u32,      // drain weight (wu)
+        drain_spend_weight in 1..=2000_u32, // drain spend weight (wu)
+        drain_dust in 100..=1000_u64,       // drain dust (sats)
+        n_drain_outputs in 1usize..150,     // the number of drain outputs
+        max_weight in common::maybe_max_weight(500u64..4_000), // optional max tx weight cap (wu)
+        alt_drain_weight in 100..=500_u32,      // drain weight of the other change option (wu)
+        alt_drain_spend_weight in 1..=2000_u32, // drain spend weight of the other change option (wu)
+    ) {
+        let params = common::StrategyParams { n_candidates, target_value, n_target_outputs, target_weight, replace, feerate, feerate_lt_diff, drain_weight, drain_spend_weight, drain_dust, n_drain_outputs , max_weight };
+        let candidates = common::gen_candidates(params.n_candidates);
+        let mut metric = params.lowest_fee_metric();
+        metric.drain_options.push(DrainWeights { output_weight: alt_drain_weight as u64, spend_weight: alt_drain_spend_weight as u64, n_outputs: n_drain_outputs });
+        common::can_eventually_find_best_solution(params, candidates, metric)?;
+    }
+
+    #[test]
+    #[cfg(not(debug_assertions))] // too slow if compiling for debug
+    fn ensure_bound_is_not_too_tight_with_drain_options(
+        n_candidates in 0..12_usize,        // candidates (n)
+        target_value in 500..500_000_u64,   // target value (sats)
+        n_target_outputs in 1usize..150,    // the number of outputs we're funding
+        target_weight in 0..10_000_u32,         // the sum of the weight of the outputs (wu)
+        replace in common::maybe_replace(0u64..10_000), // The weight of the transaction we're replacing
+        feerate in 1.0..100.0_f32,          // feerate (sats/vb)
+        feerate_lt_diff in -5.0..50.0_f32,  // longterm feerate diff (sats/vb)
+        drain_weight in 100..=500_u32,      // drain weight (wu)
+        drain_spend_weight in 1..=2000_u32, // drain spend weight (wu)
+        drain_dust in 100..=1000_u64,       // drain dust (sats)
+        n_drain_outputs in 1usize..150,     // the number of drain outputs
+        max_weight in common::maybe_max_weight(500u64..4_000), // optional max tx weight cap (wu)
+        alt_drain_weight in 100..=500_u32,      // drain weight of the other change option (wu)
+        alt_drain_spend_weight in 1..=2000_u32, // drain spend weight of the other change option (wu)
+    ) {
+        let params = common::StrategyParams { n_candidates, target_value, n_target_outputs, target_weight, replace, feerate, feerate_lt_diff, drain_weight, drain_spend_weight, drain_dust, n_drain_outputs , max_weight };
+        let candidates = common::gen_candidates(params.n_candidates);
+        let mut metric = params.lowest_fee_metric();
+        metric.drain_options.push(DrainWeights { output_weight: alt_drain_weight as u64, spend_weight: alt_drain_spend_weight as u64, n_outputs: n_drain_outputs });
+        common::ensure_bound_is_not_too_tight(params, candidates, metric)?;
+    }
+
     #[test]
     #[cfg(not(debug_assertions))] // too slow if compiling for debug
     fn identical_candidates(
@@ -87,6 +219,7 @@ proptest! {
                 weight: (32 + 4 + 4 + 1) * 4 + 64 + 32,
                 input_count: 1,
                 is_segwit: true,
+                ancestors: None,
             };
             params.n_candidates
         ];
@@ -129,6 +262,43 @@ proptest! {
         let metric = params.lowest_fee_metric();
         common::compare_against_benchmarks(params, candidates, metric)?;
     }
+
+    #[test]
+    #[cfg(not(debug_assertions))] // too slow if compiling for debug
+    fn run_bnb_top_k_finds_the_k_best_scores(
+        n_candidates in 0..12_usize,        // candidates (n)
+        target_value in 500..500_000_u64,   // target value (sats)
+        n_target_outputs in 1usize..150,    // the number of outputs we're funding
+        target_weight in 0..10_000_u32,         // the sum of the weight of the outputs (wu)
+        replace in common::maybe_replace(0u64..10_000), // The weight of the transaction we're replacing
+        feerate in 1.0..100.0_f32,          // feerate (sats/vb)
+        feerate_lt_diff in -5.0..50.0_f32,  // longterm feerate diff (sats/vb)
+        drain_weight in 100..=500_u32,      // drain weight (wu)
+        drain_spend_weight in 1..=2000_u32, // drain spend weight (wu)
+        drain_dust in 100..=1000_u64,       // drain dust (sats)
+        n_drain_outputs in 1usize..150,     // the number of drain outputs
+        max_weight in common::maybe_max_weight(500u64..4_000), // optional max tx weight cap (wu)
+        k in 1..6_usize,
+    ) {
+        let params = common::StrategyParams { n_candidates, target_value, n_target_outputs, target_weight, replace, feerate, feerate_lt_diff, drain_weight, drain_spend_weight, drain_dust, n_drain_outputs , max_weight };
+        let candidates = common::gen_candidates(params.n_candidates);
+        let target = params.target();
+        let mut metric = params.lowest_fee_metric();
+        let cs = CoinSelector::new(&candidates);
+
+        let mut exhaustive_scores = core::iter::once(cs.clone())
+            .chain(common::ExhaustiveIter::new(&cs).unwrap().filter(|(_, inclusion)| *inclusion).map(|(cs, _)| cs))
+            .filter_map(|cs| metric.score(&cs, target))
+            .collect::<Vec<_>>();
+        exhaustive_scores.sort();
+        exhaustive_scores.truncate(k);
+
+        let top_k_scores = cs
+            .run_bnb_top_k(target, params.lowest_fee_metric(), BnbOptions::new((), ()), k)
+            .map(|solutions| solutions.into_iter().map(|solution| solution.score).collect::<Vec<_>>())
+            .unwrap_or_default();
+        prop_assert_eq!(top_k_scores, exhaustive_scores);
+    }
 }
 
 proptest! {
@@ -174,6 +344,89 @@ proptest! {
     }
 }
 
+proptest! {
+    #[test]
+    #[cfg(not(debug_assertions))] // too slow if compiling for debug
+    fn can_eventually_find_best_solution_with_input_count(
+        n_candidates in 1..15_usize,        // candidates (n)
+        target_value in 500..500_000_u64,   // target value (sats)
+        n_target_outputs in 1usize..150,    // the number of outputs we're funding
+        target_weight in 0..10_000_u32,         // the sum of the weight of the outputs (wu)
+        replace in common::maybe_replace(0u64..10_000), // The weight of the transaction we're replacing
+        feerate in 1.0..100.0_f32,          // feerate (sats/vb)
+        feerate_lt_diff in -5.0..50.0_f32,  // longterm feerate diff (sats/vb)
+        drain_weight in 100..=500_u32,      // drain weight (wu)
+        drain_spend_weight in 1..=2000_u32, // drain spend weight (wu)
+        drain_dust in 100..=1000_u64,       // drain dust (sats)
+        n_drain_outputs in 1usize..150,     // the number of drain outputs
+        max_weight in common::maybe_max_weight(500u64..4_000), // optional max tx weight cap (wu)
+        min_input_count in proptest::option::of(1..6_usize), // optional min input count
+        max_input_count in proptest::option::of(1..6_usize), // optional max input count
+    ) {
+        let params = common::StrategyParams { n_candidates, target_value, n_target_outputs, target_weight, replace, feerate, feerate_lt_diff, drain_weight, drain_spend_weight, drain_dust, n_drain_outputs , max_weight };
+        let candidates = common::gen_candidates(params.n_candidates);
+        let mut target = params.target();
+        target.min_input_count = min_input_count;
+        target.max_input_count = max_input_count;
+
+        let cs = CoinSelector::new(&candidates);
+        let expected = common::exhaustive_search(&mut cs.clone(), target, &mut params.lowest_fee_metric())
+            .map(|(score, _)| score);
+        let mut selection = cs.clone();
+        let result = selection.run_bnb(target, params.lowest_fee_metric(), usize::MAX);
+        prop_assert_eq!(result.as_ref().ok().map(|&(score, _)| score), expected);
+        if result.is_ok() {
+            prop_assert!(selection.is_within_input_count(target));
+        }
+    }
+
+    #[test]
+    #[cfg(not(debug_assertions))] // too slow if compiling for debug
+    fn can_eventually_find_best_solution_with_truc(
+        n_candidates in 1..15_usize,        // candidates (n)
+        target_value in 500..500_000_u64,   // target value (sats)
+        n_target_outputs in 1usize..150,    // the number of outputs we're funding
+        target_weight in 0..10_000_u32,         // the sum of the weight of the outputs (wu)
+        replace in common::maybe_replace(0u64..10_000), // The weight of the transaction we're replacing
+        feerate in 1.0..100.0_f32,          // feerate (sats/vb)
+        feerate_lt_diff in -5.0..50.0_f32,  // longterm feerate diff (sats/vb)
+        drain_weight in 100..=500_u32,      // drain weight (wu)
+        drain_spend_weight in 1..=2000_u32, // drain spend weight (wu)
+        drain_dust in 100..=1000_u64,       // drain dust (sats)
+        n_drain_outputs in 1usize..150,     // the number of drain outputs
+        max_weight in common::maybe_max_weight(500u64..4_000), // optional max tx weight cap (wu)
+        truc in any::<bool>(),              // whether the tx follows TRUC policy
+        truc_parents in proptest::collection::vec(any::<bool>(), 15), // which parents are TRUC
+    ) {
+        let params = common::StrategyParams { n_candidates, target_value, n_target_outputs, target_weight, replace, feerate, feerate_lt_diff, drain_weight, drain_spend_weight, drain_dust, n_drain_outputs , max_weight };
+        let mut candidates = common::gen_candidates_with_ancestors(params.n_candidates);
+        for (candidate, is_truc) in candidates.iter_mut().zip(truc_parents) {
+            if let Some(ancestors) = &mut candidate.ancestors {
+                ancestors.is_truc = is_truc;
+            }
+        }
+        let mut target = params.target();
+        target.truc = truc;
+
+        let cs = CoinSelector::new(&candidates);
+        let expected = common::exhaustive_search(&mut cs.clone(), target, &mut params.lowest_fee_metric())
+            .map(|(score, _)| score);
+        let mut selection = cs.clone();
+        let result = selection.run_bnb(target, params.lowest_fee_metric(), usize::MAX);
+        prop_assert_eq!(result.as_ref().ok().map(|&(score, _)| score), expected);
+        match result {
+            Ok((_, drain)) => {
+                prop_assert!(selection.is_truc_compatible(target));
+                prop_assert!(selection.is_within_max_weight(target, drain.weights));
+            }
+            Err(NoBnbSolution::TrucPolicyViolated) => {
+                prop_assert!(cs.is_fundable(target));
+            }
+            Err(_) => {}
+        }
+    }
+}
+
 /// We wrap `LowestFee` in `Changeless` to derive a metric that finds the lowest-fee changeless
 /// solution. Constraining to changeless should never take fewer rounds than the unconstrained
 /// `LowestFee`.
@@ -232,6 +485,9 @@ fn does_not_create_change_below_spend_cost() {
             n_outputs: 1,
         },
         max_weight: None,
+        min_input_count: None,
+        max_input_count: None,
+        truc: false,
     };
 
     let candidates = vec![
@@ -240,12 +496,14 @@ fn does_not_create_change_below_spend_cost() {
             weight: 100,
             input_count: 1,
             is_segwit: true,
+            ancestors: None,
         },
         Candidate {
             value: 50_000,
             weight: 100,
             input_count: 1,
             is_segwit: true,
+            ancestors: None,
         },
         // NOTE: this input has negative effective value
         Candidate {
@@ -253,6 +511,7 @@ fn does_not_create_change_below_spend_cost() {
             weight: 100,
             input_count: 1,
             is_segwit: true,
+            ancestors: None,
         },
     ];
 
@@ -267,10 +526,11 @@ fn does_not_create_change_below_spend_cost() {
     let mut metric = LowestFee {
         long_term_feerate: FeeRate::from_sat_per_vb(1.0),
         dust_relay_feerate: FeeRate::from_sat_per_vb(1.0),
-        drain_weights,
+        drain_options: vec![drain_weights],
     };
 
-    let (score, _) = common::bnb_search(&mut cs, target, metric, 10).expect("finds solution");
+    let (score, _) =
+        common::bnb_search(&mut cs, target, metric.clone(), 10).expect("finds solution");
 
     // The optimal selection is candidate 0 alone, and it must be changeless.
     let expected = {
@@ -315,6 +575,9 @@ fn zero_fee_tx() {
             n_outputs: 1,
         },
         max_weight: None,
+        min_input_count: None,
+        max_input_count: None,
+        truc: false,
     };
 
     let candidates = vec![
@@ -323,12 +586,14 @@ fn zero_fee_tx() {
             weight: 100,
             input_count: 1,
             is_segwit: true,
+            ancestors: None,
         },
         Candidate {
             value: 50_000,
             weight: 100,
             input_count: 1,
             is_segwit: true,
+            ancestors: None,
         },
     ];
 
@@ -342,7 +607,7 @@ fn zero_fee_tx() {
     let metric = LowestFee {
         long_term_feerate,
         dust_relay_feerate: FeeRate::from_sat_per_vb(1.0),
-        drain_weights,
+        drain_options: vec![drain_weights],
     };
     let (_score, _rounds) =
         common::bnb_search(&mut cs, target, metric, 1000).expect("must find solution");
@@ -356,6 +621,7 @@ fn err_candidate(value: u64) -> Candidate {
         weight: 272, // ~1 P2WPKH input
         input_count: 1,
         is_segwit: true,
+        ancestors: None,
     }
 }
 
@@ -363,7 +629,7 @@ fn err_metric() -> LowestFee {
     LowestFee {
         long_term_feerate: FeeRate::from_sat_per_vb(1.0),
         dust_relay_feerate: FeeRate::from_sat_per_vb(1.0),
-        drain_weights: DrainWeights::TR_KEYSPEND,
+        drain_options: vec![DrainWeights::TR_KEYSPEND],
     }
 }
 
@@ -384,6 +650,9 @@ fn run_bnb_reports_insufficient_funds() {
         outputs: err_outputs(10_000_000),
         fee: TargetFee::ZERO,
         max_weight: None,
+        min_input_count: None,
+        max_input_count: None,
+        truc: false,
     };
     assert_eq!(
         cs.run_bnb(target, err_metric(), 100_000).unwrap_err(),
@@ -405,6 +674,9 @@ fn run_bnb_reports_max_weight_exceeded() {
         outputs: err_outputs(250_000),
         fee: TargetFee::ZERO,
         max_weight: Some(1),
+        min_input_count: None,
+        max_input_count: None,
+        truc: false,
     };
     assert_eq!(
         cs.run_bnb(target, err_metric(), 100_000).unwrap_err(),
@@ -412,6 +684,135 @@ fn run_bnb_reports_max_weight_exceeded() {
     );
 }
 
+#[test]
+fn run_bnb_reports_no_scored_selection() {
+    // Every selection that covers the value leaves enough excess for change, so a changeless
+    // metric scores none of them although no limit of the target is in the way.
+    let candidates = [
+        err_candidate(100_000),
+        err_candidate(100_000),
+        err_candidate(100_000),
+    ];
+    let mut cs = CoinSelector::new(&candidates);
+    let target = Target {
+        outputs: err_outputs(250_000),
+        fee: TargetFee::ZERO,
+        max_weight: None,
+        min_input_count: None,
+        max_input_count: None,
+        truc: false,
+    };
+    assert_eq!(
+        cs.run_bnb(target, Changeless(err_metric()), 100_000)
+            .unwrap_err(),
+        NoBnbSolution::NoScoredSelection,
+    );
+}
+
+#[test]
+fn run_bnb_reports_input_count_out_of_range() {
+    // The value needs all three inputs, so two are too few and four can't be reached.
+    let candidates = [
+        err_candidate(100_000),
+        err_candidate(100_000),
+        err_candidate(100_000),
+    ];
+    let mut cs = CoinSelector::new(&candidates);
+    for (min_input_count, max_input_count) in [(None, Some(2)), (Some(4), None)] {
+        let target = Target {
+            outputs: err_outputs(250_000),
+            fee: TargetFee::ZERO,
+            max_weight: None,
+            min_input_count,
+            max_input_count,
+            truc: false,
+        };
+        assert_eq!(
+            cs.run_bnb(target, err_metric(), 100_000).unwrap_err(),
+            NoBnbSolution::InputCountOutOfRange,
+        );
+    }
+}
+
+#[test]
+fn run_bnb_respects_input_count() {
+    let candidates = [
+        err_candidate(300_000),
+        err_candidate(100_000),
+        err_candidate(100_000),
+        err_candidate(100_000),
+    ];
+    let cs = CoinSelector::new(&candidates);
+    // with a feerate every extra input costs more
+    let target = |min_input_count, max_input_count| Target {
+        outputs: err_outputs(250_000),
+        fee: TargetFee::from_feerate(FeeRate::from_sat_per_vb(1.0)),
+        max_weight: None,
+        min_input_count,
+        max_input_count,
+        truc: false,
+    };
+
+    // on its own the big input is cheapest
+    let mut selection = cs.clone();
+    selection
+        .run_bnb(target(None, None), err_metric(), 100_000)
+        .expect("finds a solution");
+    assert_eq!(selection.input_count(), 1);
+
+    let mut selection = cs.clone();
+    selection
+        .run_bnb(target(Some(2), None), err_metric(), 100_000)
+        .expect("finds a solution");
+    assert_eq!(selection.input_count(), 2);
+
+    // without the big input it takes all three small ones
+    let mut selection = cs.clone();
+    selection.ban(0);
+    assert_eq!(
+        selection
+            .run_bnb(target(None, Some(2)), err_metric(), 100_000)
+            .unwrap_err(),
+        NoBnbSolution::InputCountOutOfRange,
+    );
+}
+
+#[test]
+fn select_until_target_met_respects_input_count() {
+    let candidates = [
+        err_candidate(300_000),
+        err_candidate(100_000),
+        err_candidate(100_000),
+        err_candidate(100_000),
+    ];
+    let target = |min_input_count, max_input_count| Target {
+        outputs: err_outputs(250_000),
+        fee: TargetFee::ZERO,
+        max_weight: None,
+        min_input_count,
+        max_input_count,
+        truc: false,
+    };
+
+    // keeps selecting past the value until there are enough inputs
+    let mut cs = CoinSelector::new(&candidates);
+    assert_eq!(cs.select_until_target_met(target(Some(3), None)), Ok(()));
+    assert_eq!(cs.input_count(), 3);
+
+    let mut cs = CoinSelector::new(&candidates);
+    assert_eq!(
+        cs.select_until_target_met(target(Some(5), None)),
+        Err(SelectError::InputCountOutOfRange)
+    );
+
+    let mut cs = CoinSelector::new(&candidates);
+    cs.sort_candidates_by_key(|(_, candidate)| candidate.value);
+    assert_eq!(
+        cs.select_until_target_met(target(None, Some(2))),
+        Err(SelectError::InputCountOutOfRange)
+    );
+}
+
 #[test]
 fn run_bnb_reports_round_limit() {
     // A solvable target, but zero rounds: we can't conclude infeasibility, only that we gave up.
@@ -425,12 +826,506 @@ fn run_bnb_reports_round_limit() {
         outputs: err_outputs(250_000),
         fee: TargetFee::ZERO,
         max_weight: None,
+        min_input_count: None,
+        max_input_count: None,
+        truc: false,
     };
     assert_eq!(
         cs.run_bnb(target, err_metric(), 0).unwrap_err(),
-        NoBnbSolution::RoundLimit {
-            max_rounds: 0,
-            rounds: 0,
+        NoBnbSolution::BudgetExhausted {
+            limit: BudgetLimit::Rounds(0),
+            progress: BnbProgress {
+                rounds: 0,
+                nodes: 1,
+            },
         },
     );
 }
+
+#[test]
+fn run_bnb_reports_which_budget_ran_out() {
+    let candidates = [
+        err_candidate(100_000),
+        err_candidate(100_000),
+        err_candidate(100_000),
+    ];
+    let cs = CoinSelector::new(&candidates);
+    let target = Target {
+        outputs: err_outputs(250_000),
+        fee: TargetFee::ZERO,
+        max_weight: None,
+        min_input_count: None,
+        max_input_count: None,
+        truc: false,
+    };
+    let limit = |budget: &mut dyn BnbBudget| match cs.clone().run_bnb(target, err_metric(), budget)
+    {
+        Err(NoBnbSolution::BudgetExhausted { limit, .. }) => Some(limit),
+        _ => None,
+    };
+
+    assert_eq!(limit(&mut MaxNodes(1)), Some(BudgetLimit::Nodes(1)));
+    assert_eq!(limit(&mut StopWhen(|| true)), Some(BudgetLimit::Cancelled));
+    let cancelled = AtomicBool::new(true);
+    assert_eq!(limit(&mut &cancelled), Some(BudgetLimit::Cancelled));
+    #[cfg(feature = "std")]
+    assert_eq!(
+        limit(&mut bdk_coin_select::Deadline(std::time::Instant::now())),
+        Some(BudgetLimit::Deadline)
+    );
+    // a tuple stops at the first limit that is reached
+    assert_eq!(
+        limit(&mut (100_000, MaxNodes(1))),
+        Some(BudgetLimit::Nodes(1))
+    );
+    assert_eq!(limit(&mut (0, MaxNodes(1))), Some(BudgetLimit::Rounds(0)));
+
+    // a cleared flag doesn't stop anything
+    cancelled.store(false, Ordering::Relaxed);
+    assert!(cs.clone().run_bnb(target, err_metric(), &cancelled).is_ok());
+}
+
+#[test]
+fn budget_is_not_exhausted_by_finished_search() {
+    let candidates = [
+        err_candidate(100_000),
+        err_candidate(100_000),
+        err_candidate(100_000),
+    ];
+    let cs = CoinSelector::new(&candidates);
+    for value_sum in [250_000, 10_000_000] {
+        let target = Target {
+            outputs: err_outputs(value_sum),
+            fee: TargetFee::ZERO,
+            max_weight: None,
+            min_input_count: None,
+            max_input_count: None,
+            truc: false,
+        };
+        let rounds = cs.bnb_solutions(target, err_metric()).count();
+        // a budget of exactly the rounds needed gives the same result as an unlimited one
+        assert_eq!(
+            cs.clone().run_bnb(target, err_metric(), rounds),
+            cs.clone().run_bnb(target, err_metric(), ()),
+        );
+    }
+}
+
+fn warm_start_params() -> common::StrategyParams {
+    common::StrategyParams {
+        n_candidates: 30,
+        target_value: 1_500_000,
+        n_target_outputs: 1,
+        target_weight: 200,
+        replace: None,
+        feerate: 10.0,
+        feerate_lt_diff: -5.0,
+        drain_weight: 172,
+        drain_spend_weight: 230,
+        drain_dust: 330,
+        n_drain_outputs: 1,
+        max_weight: None,
+    }
+}
+
+#[test]
+fn run_bnb_with_incumbent_finds_the_best_solution_with_fewer_rounds() {
+    let params = warm_start_params();
+    let candidates = common::gen_candidates(params.n_candidates);
+    let target = params.target();
+    let cs = CoinSelector::new(&candidates);
+
+    let mut cold_stats = BnbStats::default();
+    let best_score = cs
+        .clone()
+        .run_bnb_with(
+            target,
+            params.lowest_fee_metric(),
+            BnbOptions::new((), &mut cold_stats),
+        )
+        .expect("finds a solution")
+        .score;
+
+    let mut incumbent = cs.clone();
+    incumbent.select_until_target_met(target).expect("funded");
+    let incumbent_score = params
+        .lowest_fee_metric()
+        .score(&incumbent, target)
+        .expect("incumbent is valid");
+    assert!(incumbent_score > best_score);
+
+    let mut warm_stats = BnbStats::default();
+    let mut warm = cs.clone();
+    let solution = warm
+        .run_bnb_with_incumbent(
+            target,
+            params.lowest_fee_metric(),
+            BnbOptions::new((), &mut warm_stats),
+            &incumbent,
+        )
+        .expect("finds a solution");
+    assert_eq!(solution.score, best_score);
+    assert!(solution.proven_optimal);
+    assert!(warm.is_funded_with_drain(target, solution.drain));
+    assert!(warm_stats.rounds <= cold_stats.rounds);
+}
+
+#[test]
+fn run_bnb_with_incumbent_returns_incumbent_if_nothing_better_is_found() {
+    let params = warm_start_params();
+    let candidates = common::gen_candidates(params.n_candidates);
+    let target = params.target();
+    let cs = CoinSelector::new(&candidates);
+
+    let mut incumbent = cs.clone();
+    incumbent.select_until_target_met(target).expect("funded");
+    let mut metric = params.lowest_fee_metric();
+    let expected = (
+        metric.score(&incumbent, target).expect("valid"),
+        metric.drain(&incumbent, target),
+    );
+
+    // the budget runs out straight away
+    let mut selection = cs.clone();
+    let result = selection.run_bnb_with_incumbent(
+        target,
+        params.lowest_fee_metric(),
+        BnbOptions::new(0, ()),
+        &incumbent,
+    );
+    assert_eq!(
+        result.map(|solution| (solution.score, solution.drain)),
+        Ok(expected)
+    );
+    assert_eq!(selection.selected_indices(), incumbent.selected_indices());
+
+    // the incumbent is already the best
+    let mut best = cs.clone();
+    best.run_bnb(target, params.lowest_fee_metric(), ())
+        .expect("finds a solution");
+    let mut selection = cs.clone();
+    let result = selection.run_bnb_with_incumbent(
+        target,
+        params.lowest_fee_metric(),
+        BnbOptions::new((), ()),
+        &best,
+    );
+    assert_eq!(
+        result.map(|solution| solution.score).ok(),
+        metric.score(&best, target)
+    );
+    assert_eq!(selection.selected_indices(), best.selected_indices());
+}
+
+#[test]
+fn upper_bound_only_allows_better_solutions() {
+    let params = warm_start_params();
+    let candidates = common::gen_candidates(params.n_candidates);
+    let target = params.target();
+    let cs = CoinSelector::new(&candidates);
+    let (best_score, _) = cs
+        .clone()
+        .run_bnb(target, params.lowest_fee_metric(), ())
+        .expect("finds a solution");
+
+    let run_with_upper_bound = |upper_bound: f32| {
+        let mut options = BnbOptions::new((), ());
+        options.upper_bound = Some(Ordf32(upper_bound));
+        cs.clone()
+            .run_bnb_with(target, params.lowest_fee_metric(), options)
+            .map(|solution| solution.score)
+    };
+    assert_eq!(
+        run_with_upper_bound(best_score.0),
+        Err(NoBnbSolution::NoBetterSolution)
+    );
+    assert_eq!(run_with_upper_bound(best_score.0 + 1.0), Ok(best_score));
+}
+
+#[test]
+fn lowest_fee_only_scores_selections_that_conflict() {
+    let params = warm_start_params();
+    let candidates = common::gen_candidates(params.n_candidates);
+    let target = params.target();
+    let mut metric = params.lowest_fee_metric();
+
+    let mut cs = CoinSelector::new(&candidates);
+    cs.select_until_target_met(target).expect("funded");
+    let conflict = (0..candidates.len())
+        .find(|&index| !cs.is_selected(index))
+        .expect("some candidate isn't selected");
+    assert!(metric.score(&cs, target).is_some());
+
+    cs.add_conflict(conflict);
+    assert!(!cs.is_conflicting());
+    assert_eq!(metric.score(&cs, target), None);
+    assert!(metric.bound(&cs, target).is_some());
+
+    cs.ban(conflict);
+    assert!(!cs.can_conflict());
+    assert_eq!(metric.bound(&cs, target), None);
+
+    cs.select(conflict);
+    assert!(cs.is_conflicting());
+    assert!(metric.score(&cs, target).is_some());
+}
+
+#[test]
+fn lowest_fee_tells_apart_identical_candidates_when_only_one_is_a_conflict() {
+    let candidates = [
+        common::p2wpkh_candidate(100_000),
+        common::p2wpkh_candidate(100_000),
+    ];
+    let target = common::single_output_target(50_000, FeeRate::from_sat_per_vb(5.0));
+    let metric = LowestFee {
+        long_term_feerate: FeeRate::from_sat_per_vb(5.0),
+        dust_relay_feerate: FeeRate::from_sat_per_vb(3.0),
+        drain_options: vec![DrainWeights::TR_KEYSPEND],
+    };
+
+    let mut only_conflict = CoinSelector::new(&candidates);
+    only_conflict.add_conflict(1);
+    only_conflict.select(1);
+    let expected_score = metric
+        .clone()
+        .score(&only_conflict, target)
+        .expect("conflicts and is funded");
+
+    let mut cs = CoinSelector::new(&candidates);
+    cs.add_conflict(1);
+    let (score, _) = cs
+        .run_bnb(target, metric, usize::MAX)
+        .expect("finds a solution");
+    assert_eq!(score, expected_score);
+    assert_eq!(cs.selected_indices().iter().collect::<Vec<_>>(), vec![1]);
+}
+
+#[test]
+fn run_bnb_with_reports_the_optimality_gap() {
+    let params = warm_start_params();
+    let candidates = common::gen_candidates(params.n_candidates);
+    let target = params.target();
+    let cs = CoinSelector::new(&candidates);
+
+    let best = cs
+        .clone()
+        .run_bnb_with(target, params.lowest_fee_metric(), BnbOptions::new((), ()))
+        .expect("finds a solution");
+    assert!(best.proven_optimal);
+    assert_eq!(best.lower_bound, best.score);
+
+    // depth first finds solutions early, long before it can prove they are the best
+    let mut unproven = 0;
+    for rounds in [50, 100, 200, 500, 1_000, 2_000] {
+        let mut options = BnbOptions::new(rounds, ());
+        options.exploration = Exploration::DepthFirst;
+        let solution = match cs
+            .clone()
+            .run_bnb_with(target, params.lowest_fee_metric(), options)
+        {
+            Ok(solution) => solution,
+            Err(_) => continue,
+        };
+        eprintln!(
+            "{} {:?} {:?} {:?} {}",
+            rounds, solution.score, solution.lower_bound, best.score, solution.proven_optimal
+        );
+        // the lower bound is proven, so the best score can't be below it
+        assert!(solution.lower_bound <= best.score, "rounds: {}", rounds);
+        assert!(solution.lower_bound <= solution.score, "rounds: {}", rounds);
+        assert_eq!(
+            solution.proven_optimal,
+            solution.lower_bound == solution.score,
+            "rounds: {}",
+            rounds
+        );
+        if !solution.proven_optimal {
+            unproven += 1;
+        }
+    }
+    assert!(unproven > 0, "the budgets should stop some searches early");
+}
+
+#[test]
+fn run_bnb_top_k_returns_distinct_solutions_best_first() {
+    let params = warm_start_params();
+    let candidates = common::gen_candidates(params.n_candidates);
+    let target = params.target();
+    let cs = CoinSelector::new(&candidates);
+
+    let (best_score, _) = cs
+        .clone()
+        .run_bnb(target, params.lowest_fee_metric(), ())
+        .expect("finds a solution");
+    let solutions = cs
+        .run_bnb_top_k(
+            target,
+            params.lowest_fee_metric(),
+            BnbOptions::new((), ()),
+            3,
+        )
+        .expect("finds solutions");
+
+    assert_eq!(solutions.len(), 3);
+    assert_eq!(solutions[0].score, best_score);
+    assert!(solutions.windows(2).all(|w| w[0].score <= w[1].score));
+    for (i, solution) in solutions.iter().enumerate() {
+        assert!(solution
+            .selector
+            .is_funded_with_drain(target, solution.drain));
+        for other in &solutions[i + 1..] {
+            assert_ne!(
+                solution.selector.selected_indices(),
+                other.selector.selected_indices()
+            );
+        }
+    }
+    assert!(
+        cs.selected_indices().is_empty(),
+        "leaves the selection as is"
+    );
+
+    assert!(cs
+        .run_bnb_top_k(
+            target,
+            params.lowest_fee_metric(),
+            BnbOptions::new((), ()),
+            0
+        )
+        .expect("k of 0 is not an error")
+        .is_empty());
+}
+
+#[test]
+fn lp_min_cost_relaxes_value_and_weight_together() {
+    // the cheap candidate is heavy and the light one is expensive
+    let candidates = [
+        Candidate {
+            value: 10,
+            weight: 10,
+            input_count: 1,
+            is_segwit: true,
+            ancestors: None,
+        },
+        Candidate {
+            value: 10,
+            weight: 1,
+            input_count: 5,
+            is_segwit: true,
+            ancestors: None,
+        },
+    ];
+    let cs = CoinSelector::new(&candidates);
+    let lp_min_cost = |max_added_weight| {
+        metrics::lp_min_cost(
+            &cs,
+            10.0,
+            max_added_weight,
+            |c| c.value as f32,
+            |c| c.input_count as f32,
+        )
+    };
+
+    assert_eq!(lp_min_cost(None), Some(1.0));
+    // 4/9 of the heavy one and 5/9 of the light one fill the 5 wu
+    let cost = lp_min_cost(Some(5.0)).expect("is feasible");
+    assert!((cost - 29.0 / 9.0).abs() < 1e-3, "cost: {}", cost);
+    // even the light one is too heavy
+    assert_eq!(lp_min_cost(Some(0.5)), None);
+}
+
+#[test]
+fn lowest_fee_bound_uses_max_weight_with_ancestors() {
+    let candidates = [
+        // heavy but with nothing to bump
+        Candidate {
+            value: 200_000,
+            weight: 4_000,
+            input_count: 1,
+            is_segwit: true,
+            ancestors: None,
+        },
+        // light but has to bump its parent by 20k sats
+        Candidate {
+            value: 200_000,
+            weight: 300,
+            input_count: 1,
+            is_segwit: true,
+            ancestors: Some(Ancestors {
+                fee: 0,
+                weight: 8_000,
+                is_truc: false,
+            }),
+        },
+    ];
+    let cs = CoinSelector::new(&candidates);
+    let target = |max_weight| Target {
+        outputs: err_outputs(150_000),
+        fee: TargetFee::from_feerate(FeeRate::from_sat_per_vb(10.0)),
+        max_weight,
+        min_input_count: None,
+        max_input_count: None,
+        truc: false,
+    };
+
+    let unlimited = err_metric()
+        .bound(&cs, target(None))
+        .expect("can reach the target");
+    let limited = err_metric()
+        .bound(&cs, target(Some(2_000)))
+        .expect("can reach the target");
+    // the heavy candidate doesn't fit, so the bound has to pay for bumping the parent
+    assert!(limited > unlimited);
+
+    let mut selection = cs.clone();
+    let (score, _) = selection
+        .run_bnb(target(Some(2_000)), err_metric(), 100_000)
+        .expect("finds a solution");
+    assert!(selection.is_selected(1));
+    assert!(limited <= score);
+}
+
+/// The cheaper change output type depends on how the current feerate compares to the long-term
+/// feerate: P2WPKH is cheaper to create but more expensive to spend than P2TR.
+#[test]
+fn picks_cheapest_drain_option() {
+    let p2wpkh = DrainWeights {
+        output_weight: 31 * 4,
+        spend_weight: 272,
+        n_outputs: 1,
+    };
+    let p2tr = DrainWeights::TR_KEYSPEND;
+    let candidates = vec![Candidate {
+        value: 200_000,
+        weight: 272,
+        input_count: 1,
+        is_segwit: true,
+        ancestors: None,
+    }];
+    let mut cs = CoinSelector::new(&candidates);
+    cs.select(0);
+
+    let drain_weights_at = |feerate: f32, long_term_feerate: f32| {
+        let target = Target {
+            fee: TargetFee::from_feerate(FeeRate::from_sat_per_vb(feerate)),
+            outputs: TargetOutputs {
+                value_sum: 100_000,
+                weight_sum: 200 - TX_FIXED_FIELD_WEIGHT - 1,
+                n_outputs: 1,
+            },
+            max_weight: None,
+            min_input_count: None,
+            max_input_count: None,
+            truc: false,
+        };
+        let mut metric = LowestFee {
+            long_term_feerate: FeeRate::from_sat_per_vb(long_term_feerate),
+            dust_relay_feerate: FeeRate::from_sat_per_vb(3.0),
+            drain_options: vec![p2wpkh, p2tr],
+        };
+        metric.drain(&cs, target).weights
+    };
+
+    assert_eq!(drain_weights_at(50.0, 2.0), p2wpkh);
+    assert_eq!(drain_weights_at(2.0, 50.0), p2tr);
+}
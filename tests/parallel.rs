@@ -0,0 +1,257 @@
+#![cfg(feature = "std")]
+#![allow(unused_imports)]
+
+mod common;
+use bdk_coin_select::{
+    float::Ordf32, metrics::LowestFee, BnbMetric, BudgetLimit, CoinSelector, Drain, DrainWeights,
+    FeeRate, NoBnbSolution, Target, TargetFee, TargetOutputs,
+};
+use proptest::prelude::*;
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+fn params(n_candidates: usize, target_value: u64) -> common::StrategyParams {
+    common::StrategyParams {
+        n_candidates,
+        target_value,
+        n_target_outputs: 1,
+        target_weight: 200,
+        replace: None,
+        feerate: 10.0,
+        feerate_lt_diff: -5.0,
+        drain_weight: 172,
+        drain_spend_weight: 230,
+        drain_dust: 330,
+        n_drain_outputs: 1,
+        max_weight: None,
+    }
+}
+
+#[test]
+fn run_bnb_parallel_finds_the_same_score_as_run_bnb() {
+    let params = params(30, 1_500_000);
+    let candidates = common::gen_candidates(params.n_candidates);
+    let target = params.target();
+
+    let mut sequential = CoinSelector::new(&candidates);
+    let (expected_score, _) = sequential
+        .run_bnb(target, params.lowest_fee_metric(), usize::MAX)
+        .expect("finds a solution");
+
+    for threads in 0..5 {
+        let mut parallel = CoinSelector::new(&candidates);
+        let (score, drain) = parallel
+            .run_bnb_parallel(target, params.lowest_fee_metric(), usize::MAX, threads)
+            .expect("finds a solution");
+        assert_eq!(score, expected_score, "threads: {}", threads);
+        assert!(parallel.is_funded_with_drain(target, drain));
+    }
+}
+
+#[test]
+fn run_bnb_parallel_shares_the_budget() {
+    let params = params(30, 1_500_000);
+    let candidates = common::gen_candidates(params.n_candidates);
+
+    let mut cs = CoinSelector::new(&candidates);
+    match cs.run_bnb_parallel(params.target(), params.lowest_fee_metric(), 0_usize, 4) {
+        Err(NoBnbSolution::BudgetExhausted { limit, progress }) => {
+            assert_eq!(limit, BudgetLimit::Rounds(0));
+            assert_eq!(progress.rounds, 0);
+        }
+        other => panic!("unexpected result: {:?}", other),
+    }
+
+    // the rounds of all the threads count towards the same limit
+    match cs.run_bnb_parallel(params.target(), params.lowest_fee_metric(), 10_usize, 4) {
+        Ok(_) => {}
+        Err(NoBnbSolution::BudgetExhausted { limit, progress }) => {
+            assert_eq!(limit, BudgetLimit::Rounds(10));
+            assert_eq!(progress.rounds, 10);
+        }
+        Err(e) => panic!("unexpected error: {}", e),
+    }
+}
+
+#[test]
+fn run_bnb_parallel_takes_a_borrowed_budget() {
+    let params = params(30, 1_500_000);
+    let candidates = common::gen_candidates(params.n_candidates);
+    let cancelled = AtomicBool::new(true);
+
+    let mut cs = CoinSelector::new(&candidates);
+    match cs.run_bnb_parallel(params.target(), params.lowest_fee_metric(), &cancelled, 4) {
+        Err(NoBnbSolution::BudgetExhausted { limit, progress }) => {
+            assert_eq!(limit, BudgetLimit::Cancelled);
+            assert_eq!(progress.rounds, 0);
+        }
+        other => panic!("unexpected result: {:?}", other),
+    }
+
+    cancelled.store(false, Ordering::Relaxed);
+    let (_, drain) = cs
+        .run_bnb_parallel(params.target(), params.lowest_fee_metric(), &cancelled, 4)
+        .expect("finds a solution");
+    assert!(cs.is_funded_with_drain(params.target(), drain));
+}
+
+#[test]
+fn run_bnb_parallel_reports_insufficient_funds() {
+    let params = params(10, 100_000_000);
+    let candidates = common::gen_candidates(params.n_candidates);
+
+    let mut cs = CoinSelector::new(&candidates);
+    assert_eq!(
+        cs.run_bnb_parallel(params.target(), params.lowest_fee_metric(), usize::MAX, 4)
+            .unwrap_err(),
+        NoBnbSolution::InsufficientFunds
+    );
+}
+
+#[test]
+fn run_bnb_parallel_only_returns_selections_that_conflict() {
+    let params = params(20, 1_000_000);
+    let candidates = common::gen_candidates(params.n_candidates);
+    let target = params.target();
+
+    let mut unconstrained = CoinSelector::new(&candidates);
+    unconstrained
+        .run_bnb(target, params.lowest_fee_metric(), usize::MAX)
+        .expect("finds a solution");
+    let conflict = (0..candidates.len())
+        .find(|&index| !unconstrained.is_selected(index))
+        .expect("some candidate isn't selected");
+
+    let mut sequential = CoinSelector::new(&candidates);
+    sequential.add_conflict(conflict);
+    let mut parallel = sequential.clone();
+    let (expected_score, _) = sequential
+        .run_bnb(target, params.lowest_fee_metric(), usize::MAX)
+        .expect("finds a solution");
+    let (score, _) = parallel
+        .run_bnb_parallel(target, params.lowest_fee_metric(), usize::MAX, 4)
+        .expect("finds a solution");
+    assert_eq!(score, expected_score);
+    assert!(parallel.is_selected(conflict));
+
+    let mut banned = CoinSelector::new(&candidates);
+    banned.add_conflict(conflict);
+    banned.ban(conflict);
+    assert_eq!(
+        banned.run_bnb_parallel(target, params.lowest_fee_metric(), usize::MAX, 4),
+        Err(NoBnbSolution::NoConflict)
+    );
+}
+
+/// Panics when bounding a selection of two inputs in every thread but the first one. The first
+/// thread waits for one of the others to panic instead, so it is left waiting on them. It stops
+/// waiting at the `deadline` so that the test fails rather than hangs if none of them panics.
+struct PanickingMetric {
+    inner: LowestFee,
+    /// Counts the clones, which `run_bnb_parallel` makes for each thread in turn.
+    clones: Arc<AtomicUsize>,
+    clone_index: usize,
+    panicked: Arc<AtomicBool>,
+    deadline: Instant,
+}
+
+impl Clone for PanickingMetric {
+    fn clone(&self) -> Self {
+        PanickingMetric {
+            inner: self.inner.clone(),
+            clones: self.clones.clone(),
+            clone_index: self.clones.fetch_add(1, Ordering::SeqCst),
+            panicked: self.panicked.clone(),
+            deadline: self.deadline,
+        }
+    }
+}
+
+impl BnbMetric for PanickingMetric {
+    type Score = Ordf32;
+
+    fn drain(&mut self, cs: &CoinSelector<'_>, target: Target) -> Drain {
+        self.inner.drain(cs, target)
+    }
+
+    fn score(&mut self, cs: &CoinSelector<'_>, target: Target) -> Option<Ordf32> {
+        self.inner.score(cs, target)
+    }
+
+    fn bound(&mut self, cs: &CoinSelector<'_>, target: Target) -> Option<Ordf32> {
+        if cs.selected().count() >= 2 {
+            if self.clone_index == 0 {
+                while !self.panicked.load(Ordering::SeqCst) && Instant::now() < self.deadline {
+                    std::thread::yield_now();
+                }
+            } else {
+                self.panicked.store(true, Ordering::SeqCst);
+                panic!("metric panicked");
+            }
+        }
+        self.inner.bound(cs, target)
+    }
+
+    fn requires_ordering_by_descending_value_pwu(&self) -> bool {
+        self.inner.requires_ordering_by_descending_value_pwu()
+    }
+}
+
+#[test]
+#[should_panic(expected = "metric panicked")]
+fn run_bnb_parallel_passes_on_a_panic_of_the_metric() {
+    let params = params(30, 1_500_000);
+    let candidates = common::gen_candidates(params.n_candidates);
+    let metric = PanickingMetric {
+        inner: params.lowest_fee_metric(),
+        clones: Arc::new(AtomicUsize::new(0)),
+        clone_index: usize::MAX,
+        panicked: Arc::new(AtomicBool::new(false)),
+        deadline: Instant::now() + Duration::from_secs(10),
+    };
+
+    let mut cs = CoinSelector::new(&candidates);
+    let _ = cs.run_bnb_parallel(params.target(), metric, usize::MAX, 4);
+}
+
+proptest! {
+    #[test]
+    #[cfg(not(debug_assertions))] // too slow if compiling for debug
+    fn run_bnb_parallel_matches_run_bnb(
+        n_candidates in 1..20_usize,        // candidates (n)
+        target_value in 500..500_000_u64,   // target value (sats)
+        n_target_outputs in 1usize..150,    // the number of outputs we're funding
+        target_weight in 0..10_000_u32,         // the sum of the weight of the outputs (wu)
+        replace in common::maybe_replace(0u64..10_000), // The weight of the transaction we're replacing
+        feerate in 1.0..100.0_f32,          // feerate (sats/vb)
+        feerate_lt_diff in -5.0..50.0_f32,  // longterm feerate diff (sats/vb)
+        drain_weight in 100..=500_u32,      // drain weight (wu)
+        drain_spend_weight in 1..=2000_u32, // drain spend weight (wu)
+        drain_dust in 100..=1000_u64,       // drain dust (sats)
+        n_drain_outputs in 1usize..150,     // the number of drain outputs
+        max_weight in common::maybe_max_weight(500u64..4_000), // optional max tx weight cap (wu)
+        with_ancestors in any::<bool>(),
+        threads in 2..5_usize,
+    ) {
+        let params = common::StrategyParams { n_candidates, target_value, n_target_outputs, target_weight, replace, feerate, feerate_lt_diff, drain_weight, drain_spend_weight, drain_dust, n_drain_outputs , max_weight };
+        let candidates = if with_ancestors {
+            common::gen_candidates_with_ancestors(params.n_candidates)
+        } else {
+            common::gen_candidates(params.n_candidates)
+        };
+        let target = params.target();
+
+        let sequential = CoinSelector::new(&candidates)
+            .run_bnb(target, params.lowest_fee_metric(), usize::MAX)
+            .map(|(score, _)| score);
+        let parallel = CoinSelector::new(&candidates)
+            .run_bnb_parallel(target, params.lowest_fee_metric(), usize::MAX, threads)
+            .map(|(score, _)| score);
+        prop_assert_eq!(parallel, sequential);
+    }
+}
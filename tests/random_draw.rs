@@ -0,0 +1,110 @@
+mod common;
+use bdk_coin_select::{CoinSelector, Drain, DrainWeights, FeeRate, SelectError, Target};
+use common::{change_policy, p2wpkh_candidate, xorshift};
+use proptest::prelude::*;
+
+fn target(value: u64, max_weight: Option<u64>) -> Target {
+    Target {
+        max_weight,
+        ..common::single_output_target(value, FeeRate::from_sat_per_vb(5.0))
+    }
+}
+
+fn with_change() -> Drain {
+    Drain {
+        weights: DrainWeights::TR_KEYSPEND,
+        value: 1_000,
+    }
+}
+
+proptest! {
+    #[test]
+    fn selection_funds_target_and_change(
+        values in proptest::collection::vec(1_000..200_000_u64, 1..30),
+        target_value in 1_000..1_000_000_u64,
+        max_weight in proptest::option::of(1_000..6_000_u64),
+        seed in 1..u64::MAX,
+    ) {
+        let candidates = values.into_iter().map(p2wpkh_candidate).collect::<Vec<_>>();
+        let target = target(target_value, max_weight);
+        let mut cs = CoinSelector::new(&candidates);
+        match cs.select_random_draw(target, change_policy(), &mut xorshift(seed)) {
+            Ok(drain) => {
+                prop_assert!(cs.is_funded_with_drain(target, with_change()));
+                prop_assert!(cs.is_within_max_weight(target, change_policy().drain_weights));
+                prop_assert_eq!(drain, cs.drain(target, change_policy()));
+            }
+            Err(SelectError::InsufficientFunds(_)) => {
+                prop_assert!(cs.is_empty());
+                let mut all = cs.clone();
+                all.select_all_effective(target.fee.rate);
+                prop_assert!(!all.is_funded_with_drain(target, with_change()));
+            }
+            Err(SelectError::MaxWeightExceeded) => {
+                prop_assert!(cs.is_empty());
+                prop_assert!(max_weight.is_some());
+            }
+            Err(SelectError::InputCountOutOfRange) => {
+                prop_assert!(false, "the target doesn't limit the input count");
+            }
+            Err(SelectError::TrucPolicyViolated) => {
+                prop_assert!(false, "the candidates are all confirmed");
+            }
+        }
+    }
+}
+
+#[test]
+fn selection_depends_on_randomness() {
+    let candidates = (1..=20)
+        .map(|i| p2wpkh_candidate(i * 10_000))
+        .collect::<Vec<_>>();
+    let target = target(50_000, None);
+
+    let selections = (1..10)
+        .map(|seed| {
+            let mut cs = CoinSelector::new(&candidates);
+            cs.select_random_draw(target, change_policy(), &mut xorshift(seed))
+                .expect("finds selection");
+            cs.selected_indices().iter().collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    assert!(selections
+        .iter()
+        .any(|selection| *selection != selections[0]));
+}
+
+#[test]
+fn drops_lowest_effective_value_over_max_weight() {
+    // only two inputs fit, so the small ones get dropped in favour of the large ones
+    let mut candidates = vec![p2wpkh_candidate(2_000); 8];
+    candidates.push(p2wpkh_candidate(60_000));
+    candidates.push(p2wpkh_candidate(60_000));
+    let target = target(100_000, Some(1_000));
+
+    for seed in 1..10 {
+        let mut cs = CoinSelector::new(&candidates);
+        cs.select_random_draw(target, change_policy(), &mut xorshift(seed))
+            .expect("finds selection");
+        assert_eq!(cs.selected_indices().iter().collect::<Vec<_>>(), vec![8, 9]);
+    }
+}
+
+#[test]
+fn errors() {
+    let candidates = vec![p2wpkh_candidate(60_000), p2wpkh_candidate(60_000)];
+    let mut cs = CoinSelector::new(&candidates);
+    assert!(matches!(
+        cs.select_random_draw(target(200_000, None), change_policy(), &mut xorshift(1)),
+        Err(SelectError::InsufficientFunds(_))
+    ));
+    assert_eq!(
+        cs.select_random_draw(
+            target(100_000, Some(900)),
+            change_policy(),
+            &mut xorshift(1)
+        ),
+        Err(SelectError::MaxWeightExceeded)
+    );
+    assert!(cs.is_empty());
+}
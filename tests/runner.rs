@@ -0,0 +1,113 @@
+mod common;
+use bdk_coin_select::metrics::LowestFee;
+use bdk_coin_select::{
+    CoinSelector, Comparison, DrainWeights, FeeRate, SelectionRunner, Strategy, StrategyError,
+    Target,
+};
+use common::{p2wpkh_candidate, xorshift};
+
+fn target(value: u64) -> Target {
+    common::single_output_target(value, FeeRate::from_sat_per_vb(10.0))
+}
+
+fn runner(comparison: Comparison) -> SelectionRunner {
+    SelectionRunner {
+        metric: LowestFee {
+            long_term_feerate: FeeRate::from_sat_per_vb(5.0),
+            dust_relay_feerate: FeeRate::from_sat_per_vb(3.0),
+            drain_options: vec![DrainWeights::TR_KEYSPEND],
+        },
+        comparison,
+        strategies: Strategy::ALL.to_vec(),
+        max_rounds: 10_000,
+    }
+}
+
+#[test]
+fn picks_the_lowest_scoring_strategy() {
+    let candidates = (1..=20)
+        .map(|i| p2wpkh_candidate(i * 7_919))
+        .collect::<Vec<_>>();
+    let cs = CoinSelector::new(&candidates);
+
+    for &comparison in &[Comparison::Waste, Comparison::LongTermFee] {
+        let result = runner(comparison)
+            .run(&cs, target(200_000), &mut xorshift(42))
+            .expect("finds a selection");
+
+        assert_eq!(result.attempts.len(), Strategy::ALL.len());
+        let winner = result
+            .attempts
+            .iter()
+            .find(|attempt| attempt.strategy == result.strategy)
+            .expect("winner was attempted");
+        assert_eq!(winner.result, Ok(result.score));
+        for attempt in &result.attempts {
+            if let Ok(score) = attempt.result {
+                assert!(result.score <= score);
+            }
+        }
+        assert!(result.selector.is_funded(target(200_000)));
+    }
+}
+
+#[test]
+fn lowest_fee_bnb_never_loses_on_long_term_fee() {
+    let candidates = (1..=20)
+        .map(|i| p2wpkh_candidate(i * 7_919))
+        .collect::<Vec<_>>();
+    let cs = CoinSelector::new(&candidates);
+
+    let result = runner(Comparison::LongTermFee)
+        .run(&cs, target(200_000), &mut xorshift(7))
+        .expect("finds a selection");
+    // The other strategies can only tie with the optimum, and ties go to the first strategy.
+    assert_eq!(result.strategy, Strategy::LowestFeeBnb);
+}
+
+#[test]
+fn reports_every_failure() {
+    let candidates = vec![p2wpkh_candidate(10_000), p2wpkh_candidate(20_000)];
+    let cs = CoinSelector::new(&candidates);
+
+    let err = runner(Comparison::Waste)
+        .run(&cs, target(100_000), &mut xorshift(1))
+        .expect_err("can't fund the target");
+    assert_eq!(err.attempts.len(), Strategy::ALL.len());
+    for attempt in &err.attempts {
+        match attempt.result {
+            Err(StrategyError::Bnb(_)) => assert!(matches!(
+                attempt.strategy,
+                Strategy::LowestFeeBnb | Strategy::ChangelessBnb
+            )),
+            Err(StrategyError::Select(_)) => assert!(matches!(
+                attempt.strategy,
+                Strategy::LargestFirst | Strategy::RandomDraw
+            )),
+            Err(StrategyError::NoConflict) => panic!("there are no conflicts"),
+            Ok(_) => panic!("{:?} should fail", attempt.strategy),
+        }
+    }
+}
+
+#[test]
+fn only_picks_selections_that_conflict() {
+    let candidates = (1..=20)
+        .map(|i| p2wpkh_candidate(i * 7_919))
+        .collect::<Vec<_>>();
+    let mut cs = CoinSelector::new(&candidates);
+    // the smallest candidate, which largest first never gets to
+    cs.add_conflict(0);
+
+    let mut largest_first = runner(Comparison::Waste);
+    largest_first.strategies = vec![Strategy::LargestFirst];
+    let err = largest_first
+        .run(&cs, target(200_000), &mut xorshift(3))
+        .expect_err("largest first doesn't select the conflict");
+    assert_eq!(err.attempts[0].result, Err(StrategyError::NoConflict));
+
+    let result = runner(Comparison::Waste)
+        .run(&cs, target(200_000), &mut xorshift(3))
+        .expect("branch and bound finds a selection");
+    assert!(result.selector.is_conflicting());
+}
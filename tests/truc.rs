@@ -0,0 +1,183 @@
+mod common;
+use bdk_coin_select::{
+    metrics::LowestFee, Ancestors, CoinSelector, DrainWeights, FeeRate, NoBnbSolution, SelectError,
+    Target, TRUC_CHILD_MAX_WEIGHT, TRUC_MAX_WEIGHT,
+};
+use common::{p2wpkh_candidate, p2wpkh_candidate_with_ancestors};
+
+/// A 200 vbyte parent that paid 10 sat/vb, so spending it costs no bump fee at the target feerate.
+fn parent(is_truc: bool) -> Ancestors {
+    Ancestors {
+        fee: 2_000,
+        weight: 800,
+        is_truc,
+    }
+}
+
+fn target(value: u64, truc: bool) -> Target {
+    Target {
+        truc,
+        ..common::single_output_target(value, FeeRate::from_sat_per_vb(10.0))
+    }
+}
+
+fn metric() -> LowestFee {
+    LowestFee {
+        long_term_feerate: FeeRate::from_sat_per_vb(10.0),
+        dust_relay_feerate: FeeRate::from_sat_per_vb(3.0),
+        drain_options: vec![DrainWeights::TR_KEYSPEND],
+    }
+}
+
+#[test]
+fn weight_limit_depends_on_spending_unconfirmed_outputs() {
+    let candidates = vec![
+        p2wpkh_candidate(100_000),
+        p2wpkh_candidate_with_ancestors(100_000, Some(parent(true))),
+    ];
+    let mut cs = CoinSelector::new(&candidates);
+    assert_eq!(cs.weight_limit(target(50_000, false)), None);
+    assert_eq!(cs.weight_limit(target(50_000, true)), Some(TRUC_MAX_WEIGHT));
+
+    cs.select(0);
+    assert_eq!(cs.weight_limit(target(50_000, true)), Some(TRUC_MAX_WEIGHT));
+    cs.select(1);
+    assert_eq!(
+        cs.weight_limit(target(50_000, true)),
+        Some(TRUC_CHILD_MAX_WEIGHT)
+    );
+
+    // the lower of `max_weight` and the TRUC limit applies
+    let mut capped = target(50_000, true);
+    capped.max_weight = Some(2_000);
+    assert_eq!(cs.weight_limit(capped), Some(2_000));
+    capped.max_weight = Some(100_000);
+    assert_eq!(cs.weight_limit(capped), Some(TRUC_CHILD_MAX_WEIGHT));
+}
+
+#[test]
+fn truc_child_spends_at_most_one_unconfirmed_truc_parent() {
+    let candidates = vec![
+        p2wpkh_candidate(100_000),
+        p2wpkh_candidate_with_ancestors(100_000, Some(parent(true))),
+        p2wpkh_candidate_with_ancestors(100_000, Some(parent(true))),
+        p2wpkh_candidate_with_ancestors(100_000, Some(parent(false))),
+    ];
+    let truc = target(50_000, true);
+    let is_compatible = |indices: &[usize]| {
+        let mut cs = CoinSelector::new(&candidates);
+        for &index in indices {
+            cs.select(index);
+        }
+        cs.is_truc_compatible(truc)
+    };
+
+    assert!(is_compatible(&[]));
+    assert!(is_compatible(&[0]));
+    assert!(is_compatible(&[0, 1]));
+    assert!(!is_compatible(&[1, 2]), "two unconfirmed parents");
+    assert!(!is_compatible(&[3]), "the parent isn't TRUC");
+    assert!(!is_compatible(&[0, 1, 3]));
+}
+
+#[test]
+fn non_truc_tx_cant_spend_unconfirmed_truc_outputs() {
+    let candidates = vec![
+        p2wpkh_candidate_with_ancestors(100_000, Some(parent(false))),
+        p2wpkh_candidate_with_ancestors(100_000, Some(parent(false))),
+        p2wpkh_candidate_with_ancestors(100_000, Some(parent(true))),
+    ];
+    let target = target(50_000, false);
+    let mut cs = CoinSelector::new(&candidates);
+    cs.select(0);
+    cs.select(1);
+    assert!(cs.is_truc_compatible(target));
+    cs.select(2);
+    assert!(!cs.is_truc_compatible(target));
+}
+
+#[test]
+fn select_until_target_met_reports_truc_policy_violation() {
+    let candidates = vec![
+        p2wpkh_candidate_with_ancestors(40_000, Some(parent(true))),
+        p2wpkh_candidate_with_ancestors(40_000, Some(parent(true))),
+    ];
+    let mut cs = CoinSelector::new(&candidates);
+    assert_eq!(
+        cs.select_until_target_met(target(50_000, true)),
+        Err(SelectError::TrucPolicyViolated)
+    );
+}
+
+#[test]
+fn bnb_spends_a_single_truc_parent() {
+    let candidates = vec![
+        p2wpkh_candidate_with_ancestors(60_000, Some(parent(true))),
+        p2wpkh_candidate_with_ancestors(40_000, Some(parent(true))),
+        p2wpkh_candidate(30_000),
+    ];
+    let target = target(80_000, true);
+    let mut cs = CoinSelector::new(&candidates);
+    let (_score, drain) = cs
+        .run_bnb(target, metric(), usize::MAX)
+        .expect("finds solution");
+    assert!(cs.is_truc_compatible(target));
+    assert!(cs.is_funded_with_drain(target, drain));
+    assert_eq!(cs.selected_indices().iter().collect::<Vec<_>>(), vec![0, 2]);
+}
+
+#[test]
+fn bnb_reports_truc_policy_violation() {
+    let candidates = vec![
+        p2wpkh_candidate_with_ancestors(60_000, Some(parent(true))),
+        p2wpkh_candidate_with_ancestors(40_000, Some(parent(true))),
+    ];
+    let mut cs = CoinSelector::new(&candidates);
+    assert_eq!(
+        cs.run_bnb(target(80_000, true), metric(), usize::MAX),
+        Err(NoBnbSolution::TrucPolicyViolated)
+    );
+
+    // spending both is fine without TRUC
+    let candidates = vec![
+        p2wpkh_candidate_with_ancestors(60_000, Some(parent(false))),
+        p2wpkh_candidate_with_ancestors(40_000, Some(parent(false))),
+    ];
+    let mut cs = CoinSelector::new(&candidates);
+    assert!(cs
+        .run_bnb(target(80_000, false), metric(), usize::MAX)
+        .is_ok());
+}
+
+#[test]
+fn bnb_keeps_truc_child_within_its_weight_limit() {
+    // funding the target takes at least 16 inputs, which weigh more than a TRUC child may
+    let mut candidates = (0..20)
+        .map(|_| p2wpkh_candidate(10_000))
+        .collect::<Vec<_>>();
+    candidates.push(p2wpkh_candidate_with_ancestors(15_000, Some(parent(true))));
+    let target = target(150_000, true);
+    let mut cs = CoinSelector::new(&candidates);
+    let (_score, drain) = cs
+        .run_bnb(target, metric(), usize::MAX)
+        .expect("finds solution");
+    assert!(!cs.is_selected(20));
+    assert!(cs.is_within_max_weight(target, drain.weights));
+    assert!(cs.weight(target.outputs, drain.weights) > TRUC_CHILD_MAX_WEIGHT);
+}
+
+#[test]
+fn bnb_tells_apart_confirmed_and_unconfirmed_truc_candidates() {
+    // a non-TRUC transaction can't spend the first candidate, but it can spend the second
+    let candidates = vec![
+        p2wpkh_candidate_with_ancestors(100_000, Some(parent(true))),
+        p2wpkh_candidate(100_000),
+    ];
+    let target = target(50_000, false);
+    let mut cs = CoinSelector::new(&candidates);
+    let (_score, drain) = cs
+        .run_bnb(target, metric(), usize::MAX)
+        .expect("finds solution");
+    assert!(cs.is_funded_with_drain(target, drain));
+    assert_eq!(cs.selected_indices().iter().collect::<Vec<_>>(), vec![1]);
+}
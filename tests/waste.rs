@@ -0,0 +1,154 @@
+#![allow(unused_imports)]
+
+mod common;
+use bdk_coin_select::metrics::Waste;
+use bdk_coin_select::{
+    BnbMetric, Candidate, CoinSelector, Drain, DrainWeights, FeeRate, Target, TargetFee,
+    TargetOutputs, TX_FIXED_FIELD_WEIGHT,
+};
+use common::p2wpkh_candidate;
+use proptest::prelude::*;
+
+proptest! {
+    #![proptest_config(ProptestConfig {
+        ..Default::default()
+    })]
+
+    #[test]
+    #[cfg(not(debug_assertions))] // too slow if compiling for debug
+    fn can_eventually_find_best_solution(
+        n_candidates in 1..15_usize,        // candidates (n)
+        target_value in 500..500_000_u64,   // target value (sats)
+        n_target_outputs in 1usize..150,    // the number of outputs we're funding
+        target_weight in 0..10_000_u32,         // the sum of the weight of the outputs (wu)
+        replace in common::maybe_replace(0u64..10_000), // The weight of the transaction we're replacing
+        feerate in 1.0..100.0_f32,          // feerate (sats/vb)
+        feerate_lt_diff in -5.0..50.0_f32,  // longterm feerate diff (sats/vb)
+        drain_weight in 100..=500_u32,      // drain weight (wu)
+        drain_spend_weight in 1..=2000_u32, // drain spend weight (wu)
+        drain_dust in 100..=1000_u64,       // drain dust (sats)
+        n_drain_outputs in 1usize..150,     // the number of drain outputs
+        max_weight in common::maybe_max_weight(500u64..4_000), // optional max tx weight cap (wu)
+        excess_discount in 0.0..=1.0_f32,   // how much of a changeless excess counts as waste
+    ) {
+        let params = common::StrategyParams { n_candidates, target_value, n_target_outputs, target_weight, replace, feerate, feerate_lt_diff, drain_weight, drain_spend_weight, drain_dust, n_drain_outputs, max_weight };
+        let candidates = common::gen_candidates(params.n_candidates);
+        let metric = params.waste_metric(excess_discount);
+        common::can_eventually_find_best_solution(params, candidates, metric)?;
+    }
+
+    #[test]
+    #[cfg(not(debug_assertions))] // too slow if compiling for debug
+    fn ensure_bound_is_not_too_tight(
+        n_candidates in 0..12_usize,        // candidates (n)
+        target_value in 500..500_000_u64,   // target value (sats)
+        n_target_outputs in 1usize..150,    // the number of outputs we're funding
+        target_weight in 0..10_000_u32,         // the sum of the weight of the outputs (wu)
+        replace in common::maybe_replace(0u64..10_000), // The weight of the transaction we're replacing
+        feerate in 1.0..100.0_f32,          // feerate (sats/vb)
+        feerate_lt_diff in -5.0..50.0_f32,  // longterm feerate diff (sats/vb)
+        drain_weight in 100..=500_u32,      // drain weight (wu)
+        drain_spend_weight in 1..=2000_u32, // drain spend weight (wu)
+        drain_dust in 100..=1000_u64,       // drain dust (sats)
+        n_drain_outputs in 1usize..150,     // the number of drain outputs
+        max_weight in common::maybe_max_weight(500u64..4_000), // optional max tx weight cap (wu)
+        excess_discount in 0.0..=1.0_f32,   // how much of a changeless excess counts as waste
+    ) {
+        let params = common::StrategyParams { n_candidates, target_value, n_target_outputs, target_weight, replace, feerate, feerate_lt_diff, drain_weight, drain_spend_weight, drain_dust, n_drain_outputs, max_weight };
+        let candidates = common::gen_candidates(params.n_candidates);
+        let metric = params.waste_metric(excess_discount);
+        common::ensure_bound_is_not_too_tight(params, candidates, metric)?;
+    }
+
+    #[test]
+    #[cfg(not(debug_assertions))] // too slow if compiling for debug
+    fn ensure_bound_is_not_too_tight_with_ancestors(
+        n_candidates in 0..12_usize,        // candidates (n)
+        target_value in 500..500_000_u64,   // target value (sats)
+        n_target_outputs in 1usize..150,    // the number of outputs we're funding
+        target_weight in 0..10_000_u32,         // the sum of the weight of the outputs (wu)
+        replace in common::maybe_replace(0u64..10_000), // The weight of the transaction we're replacing
+        feerate in 1.0..100.0_f32,          // feerate (sats/vb)
+        feerate_lt_diff in -5.0..50.0_f32,  // longterm feerate diff (sats/vb)
+        drain_weight in 100..=500_u32,      // drain weight (wu)
+        drain_spend_weight in 1..=2000_u32, // drain spend weight (wu)
+        drain_dust in 100..=1000_u64,       // drain dust (sats)
+        n_drain_outputs in 1usize..150,     // the number of drain outputs
+        max_weight in common::maybe_max_weight(500u64..4_000), // optional max tx weight cap (wu)
+        excess_discount in 0.0..=1.0_f32,   // how much of a changeless excess counts as waste
+    ) {
+        let params = common::StrategyParams { n_candidates, target_value, n_target_outputs, target_weight, replace, feerate, feerate_lt_diff, drain_weight, drain_spend_weight, drain_dust, n_drain_outputs, max_weight };
+        let candidates = common::gen_candidates_with_ancestors(params.n_candidates);
+        let metric = params.waste_metric(excess_discount);
+        common::ensure_bound_is_not_too_tight(params, candidates, metric)?;
+    }
+}
+
+fn waste_target(feerate: FeeRate) -> Target {
+    common::single_output_target(100_000, feerate)
+}
+
+/// When the feerate is above the long-term feerate every input is wasteful, so the metric prefers
+/// spending a single large input over several small ones.
+#[test]
+fn high_feerate_prefers_fewer_inputs() {
+    let candidates = vec![
+        p2wpkh_candidate(40_000),
+        p2wpkh_candidate(40_000),
+        p2wpkh_candidate(40_000),
+        p2wpkh_candidate(150_000),
+    ];
+    let target = waste_target(FeeRate::from_sat_per_vb(50.0));
+    let metric = Waste {
+        long_term_feerate: FeeRate::from_sat_per_vb(5.0),
+        dust_relay_feerate: FeeRate::from_sat_per_vb(3.0),
+        drain_weights: DrainWeights::TR_KEYSPEND,
+        excess_discount: 1.0,
+    };
+
+    let mut cs = CoinSelector::new(&candidates);
+    let (_score, drain) = cs.run_bnb(target, metric, 10_000).expect("finds solution");
+    assert_eq!(cs.selected_indices().iter().collect::<Vec<_>>(), vec![3]);
+    assert!(drain.is_some(), "excess is large enough to be worth change");
+}
+
+/// When the feerate is below the long-term feerate every input reduces future waste, so the metric
+/// consolidates as many inputs as it can.
+#[test]
+fn low_feerate_consolidates() {
+    let candidates = vec![
+        p2wpkh_candidate(40_000),
+        p2wpkh_candidate(40_000),
+        p2wpkh_candidate(40_000),
+        p2wpkh_candidate(150_000),
+    ];
+    let target = waste_target(FeeRate::from_sat_per_vb(1.0));
+    let metric = Waste {
+        long_term_feerate: FeeRate::from_sat_per_vb(20.0),
+        dust_relay_feerate: FeeRate::from_sat_per_vb(3.0),
+        drain_weights: DrainWeights::TR_KEYSPEND,
+        excess_discount: 1.0,
+    };
+
+    let mut cs = CoinSelector::new(&candidates);
+    let (_score, drain) = cs.run_bnb(target, metric, 10_000).expect("finds solution");
+    assert_eq!(cs.selected_indices().len(), candidates.len());
+    assert!(drain.is_some());
+}
+
+/// With no excess discount a changeless overshoot costs nothing, so the metric never adds change.
+#[test]
+fn zero_excess_discount_is_changeless() {
+    let candidates = vec![p2wpkh_candidate(150_000), p2wpkh_candidate(40_000)];
+    let target = waste_target(FeeRate::from_sat_per_vb(10.0));
+    let mut metric = Waste {
+        long_term_feerate: FeeRate::from_sat_per_vb(5.0),
+        dust_relay_feerate: FeeRate::from_sat_per_vb(3.0),
+        drain_weights: DrainWeights::TR_KEYSPEND,
+        excess_discount: 0.0,
+    };
+
+    let mut cs = CoinSelector::new(&candidates);
+    cs.select(0);
+    assert!(metric.drain(&cs, target).is_none());
+}
@@ -0,0 +1,125 @@
+#![allow(unused_imports)]
+
+mod common;
+use bdk_coin_select::metrics::{LowestFee, Weighted};
+use bdk_coin_select::{
+    BnbMetric, Candidate, CoinSelector, Drain, DrainWeights, FeeRate, Target, TargetFee,
+    TargetOutputs, TX_FIXED_FIELD_WEIGHT,
+};
+use common::{p2wpkh_candidate, FewestInputs};
+use proptest::prelude::*;
+
+proptest! {
+    #![proptest_config(ProptestConfig {
+        ..Default::default()
+    })]
+
+    #[test]
+    #[cfg(not(debug_assertions))] // too slow if compiling for debug
+    fn can_eventually_find_best_solution(
+        n_candidates in 1..15_usize,        // candidates (n)
+        target_value in 500..500_000_u64,   // target value (sats)
+        n_target_outputs in 1usize..150,    // the number of outputs we're funding
+        target_weight in 0..10_000_u32,         // the sum of the weight of the outputs (wu)
+        replace in common::maybe_replace(0u64..10_000), // The weight of the transaction we're replacing
+        feerate in 1.0..100.0_f32,          // feerate (sats/vb)
+        feerate_lt_diff in -5.0..50.0_f32,  // longterm feerate diff (sats/vb)
+        drain_weight in 100..=500_u32,      // drain weight (wu)
+        drain_spend_weight in 1..=2000_u32, // drain spend weight (wu)
+        drain_dust in 100..=1000_u64,       // drain dust (sats)
+        n_drain_outputs in 1usize..150,     // the number of drain outputs
+        max_weight in common::maybe_max_weight(500u64..4_000), // optional max tx weight cap (wu)
+        primary_weight in 0.0..10.0_f32,    // weight of the fee score
+        secondary_weight in 0.0..10_000.0_f32, // weight of the input count score
+    ) {
+        let params = common::StrategyParams { n_candidates, target_value, n_target_outputs, target_weight, replace, feerate, feerate_lt_diff, drain_weight, drain_spend_weight, drain_dust, n_drain_outputs, max_weight };
+        let candidates = common::gen_candidates(params.n_candidates);
+        let metric = Weighted { primary: params.lowest_fee_metric(), primary_weight, secondary: FewestInputs, secondary_weight };
+        common::can_eventually_find_best_solution(params, candidates, metric)?;
+    }
+
+    #[test]
+    #[cfg(not(debug_assertions))] // too slow if compiling for debug
+    fn ensure_bound_is_not_too_tight(
+        n_candidates in 0..12_usize,        // candidates (n)
+        target_value in 500..500_000_u64,   // target value (sats)
+        n_target_outputs in 1usize..150,    // the number of outputs we're funding
+        target_weight in 0..10_000_u32,         // the sum of the weight of the outputs (wu)
+        replace in common::maybe_replace(0u64..10_000), // The weight of the transaction we're replacing
+        feerate in 1.0..100.0_f32,          // feerate (sats/vb)
+        feerate_lt_diff in -5.0..50.0_f32,  // longterm feerate diff (sats/vb)
+        drain_weight in 100..=500_u32,      // drain weight (wu)
+        drain_spend_weight in 1..=2000_u32, // drain spend weight (wu)
+        drain_dust in 100..=1000_u64,       // drain dust (sats)
+        n_drain_outputs in 1usize..150,     // the number of drain outputs
+        max_weight in common::maybe_max_weight(500u64..4_000), // optional max tx weight cap (wu)
+        primary_weight in 0.0..10.0_f32,    // weight of the fee score
+        secondary_weight in 0.0..10_000.0_f32, // weight of the input count score
+    ) {
+        let params = common::StrategyParams { n_candidates, target_value, n_target_outputs, target_weight, replace, feerate, feerate_lt_diff, drain_weight, drain_spend_weight, drain_dust, n_drain_outputs, max_weight };
+        let candidates = common::gen_candidates(params.n_candidates);
+        let metric = Weighted { primary: params.lowest_fee_metric(), primary_weight, secondary: FewestInputs, secondary_weight };
+        common::ensure_bound_is_not_too_tight(params, candidates, metric)?;
+    }
+}
+
+fn setup() -> (Vec<Candidate>, Target, LowestFee) {
+    let candidates = vec![
+        p2wpkh_candidate(60_000),
+        p2wpkh_candidate(60_000),
+        // a single input that is much heavier than the two above together
+        Candidate {
+            value: 200_000,
+            weight: 1_000,
+            input_count: 1,
+            is_segwit: false,
+            ancestors: None,
+        },
+    ];
+    let target = common::single_output_target(100_000, FeeRate::from_sat_per_vb(10.0));
+    let lowest_fee = LowestFee {
+        long_term_feerate: FeeRate::from_sat_per_vb(10.0),
+        dust_relay_feerate: FeeRate::from_sat_per_vb(3.0),
+        drain_options: vec![DrainWeights::TR_KEYSPEND],
+    };
+    (candidates, target, lowest_fee)
+}
+
+/// With no weight on the secondary metric the primary metric decides: the two light inputs are
+/// cheaper to spend.
+#[test]
+fn primary_metric_alone() {
+    let (candidates, target, lowest_fee) = setup();
+
+    let mut expected = CoinSelector::new(&candidates);
+    let (_, expected_drain) = expected
+        .run_bnb(target, lowest_fee.clone(), 10_000)
+        .expect("finds solution");
+
+    let metric = Weighted {
+        primary: lowest_fee,
+        primary_weight: 1.0,
+        secondary: FewestInputs,
+        secondary_weight: 0.0,
+    };
+    let mut cs = CoinSelector::new(&candidates);
+    let (_, drain) = cs.run_bnb(target, metric, 10_000).expect("finds solution");
+    assert_eq!(cs.selected_indices(), expected.selected_indices());
+    assert_eq!(drain, expected_drain);
+    assert_eq!(cs.selected_indices().iter().collect::<Vec<_>>(), vec![0, 1]);
+}
+
+/// Once an input costs more than the fee difference, the single heavy input is preferred.
+#[test]
+fn secondary_metric_outweighs_fee_difference() {
+    let (candidates, target, lowest_fee) = setup();
+    let metric = Weighted {
+        primary: lowest_fee,
+        primary_weight: 1.0,
+        secondary: FewestInputs,
+        secondary_weight: 10_000.0,
+    };
+    let mut cs = CoinSelector::new(&candidates);
+    cs.run_bnb(target, metric, 10_000).expect("finds solution");
+    assert_eq!(cs.selected_indices().iter().collect::<Vec<_>>(), vec![2]);
+}